@@ -1,27 +1,129 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-pub use self::erc20::{Error, Erc20, Result};
+pub use self::erc20::{Error, Erc20, IssueProposal, Result, RoleId, TokenInfo, BRIDGE, COMPLIANCE};
 use ink_lang as ink;
 
 #[ink::contract]
 pub mod erc20 {
     use ink_storage::collections::HashMap as StorageHashMap;
+    use ink_storage::collections::Vec as StorageVec;
+    use ink_storage::lazy::LazyHashMap;
+    use ink_env::hash::{Blake2x256, HashOutput};
+    use ink_prelude::string::String;
     use ink_prelude::vec::Vec;
+    use ink_env::call::{build_call, utils::ReturnType, ExecutionInput, Selector};
+    use ink_storage::traits::{PackedLayout, SpreadLayout};
 
     // 定义数据存储，参考 ERC20 标准，不过根据 RUST 的规范，将驼峰式修改为下划线命名法
     #[ink(storage)]
     pub struct Erc20 {
         creater: AccountId,
         // 代币名称
-        name: Vec<u8>,
+        name: String,
         // 代币标识
-        symbol: Vec<u8>,
+        symbol: String,
+        // 代币精度
+        decimals: u8,
         // 定义代币供应总量
         total_supply:Balance,
-        // 存储各个账号的余额
-        balances : StorageHashMap<AccountId, Balance>,
-        // 授权某人可以使用自己的余额
-        allowances : StorageHashMap<(AccountId, AccountId), Balance>,
+        // 存储各个账号持有的份额，余额 = 份额 / shares_per_token，rebase 时只需要修改 shares_per_token
+        // 使用 LazyHashMap 按需读写单个存储单元，不会像 StorageHashMap 那样随持有人增多而加载更多的数据
+        shares : LazyHashMap<AccountId, Balance, Blake2x256>,
+        // 授权某人可以使用自己的余额，第二个值为授权的过期时间，None 表示永不过期
+        allowances : LazyHashMap<(AccountId, AccountId), (Balance, Option<Timestamp>), Blake2x256>,
+        // 严格授权模式，开启后修改授权额度前必须先将授权清零
+        strict_approvals: bool,
+        // 单笔转账的最大数量，None 表示不限制
+        max_transfer_amount: Option<Balance>,
+        // 不受单笔转账上限限制的账号
+        transfer_limit_exempt: StorageHashMap<AccountId, ()>,
+        // 同一账号两次转出之间需要间隔的区块数，0 表示不限制
+        cooldown_blocks: BlockNumber,
+        // 各个账号最后一次转出时的区块高度
+        last_transfer_block: StorageHashMap<AccountId, BlockNumber>,
+        // 所有余额不为 0 的账号，用于分页查询持有人
+        holders: StorageVec<AccountId>,
+        // 持有人在 holders 中的位置，删除时用最后一个持有人填补空位
+        holder_index: StorageHashMap<AccountId, u32>,
+        // 余额从 0 变为非 0 的累计次数
+        holders_added: u64,
+        // 余额从非 0 变为 0 的累计次数
+        holders_removed: u64,
+        // 包装原生代币模式，开启后可以用原生代币 1:1 兑换本代币
+        wrapped: bool,
+        // 单次空投最多可以包含的接收账号数量
+        max_airdrop_recipients: u32,
+        // 领取空投的 merkle 树根，叶子为 (账号, 数量) 编码后的 BLAKE2 哈希
+        claim_root: Option<Hash>,
+        // 通过 merkle 证明最多可以领取的总量
+        claim_total: Balance,
+        // 已经领取的总量
+        claimed_amount: Balance,
+        // 已经领取过的叶子
+        claimed_leaves: StorageHashMap<Hash, ()>,
+        // 角色授权，(角色, 账号) 存在表示该账号拥有该角色
+        roles: StorageHashMap<(RoleId, AccountId), ()>,
+        // 已经处理过的跨链转入，(来源链, nonce)，防止重放
+        bridge_in_nonces: StorageHashMap<(u32, u64), ()>,
+        // 跨链转出的 nonce，每次转出递增
+        bridge_out_nonce: u64,
+        // 增发需要等待的区块数，增发先排队，到期后才能执行
+        issue_delay: BlockNumber,
+        // 排队中的增发，id -> (数量, 可以执行的区块高度)
+        queued_issues: StorageHashMap<u64, (Balance, BlockNumber)>,
+        // 下一个排队增发的 id
+        next_issue_id: u64,
+        // 多签增发的签名人，设置后增发只能通过多签提案进行
+        signers: StorageVec<AccountId>,
+        // 执行多签提案需要的确认数
+        threshold: u32,
+        // 多签增发提案，id -> 提案
+        issue_proposals: StorageHashMap<u64, IssueProposal>,
+        // 提案的确认记录，(提案 id, 签名人)
+        proposal_confirmations: StorageHashMap<(u64, AccountId), ()>,
+        // 下一个多签提案的 id
+        next_proposal_id: u64,
+        // 操作员授权，(账号, 操作员) 存在表示操作员可以转走该账号的任意数量
+        operators: StorageHashMap<(AccountId, AccountId), ()>,
+        // 是否只允许授权给白名单中的账号
+        spender_allowlist_enabled: bool,
+        // 允许被授权的账号白名单
+        allowed_spenders: StorageHashMap<AccountId, ()>,
+        // 各个账号用于链下签名的 nonce，每使用一次签名递增，防止签名被重放
+        nonces: StorageHashMap<AccountId, u64>,
+        // 每个份额累计可分得的分红（原生代币），放大 DIVIDEND_MAGNITUDE 倍保存精度
+        // 按份额而不是余额记录，rebase 改变余额时已经分配的分红不会变化
+        dividend_per_share: u128,
+        // 各个账号上次结算分红时的 dividend_per_share
+        dividend_index_of: StorageHashMap<AccountId, u128>,
+        // 各个账号已结算但还没有领取的分红
+        dividends_owed: StorageHashMap<AccountId, Balance>,
+        // 每笔转账销毁的比例，单位为万分之一
+        burn_bps: u16,
+        // 总供应量上限，None 表示不限制
+        cap: Option<Balance>,
+        // 每个周期最多可以增发的数量，None 表示不限制
+        max_mint_per_period: Option<Balance>,
+        // 增发限额的周期长度（区块数）
+        period_blocks: BlockNumber,
+        // 当前周期的编号，即区块高度除以周期长度
+        mint_period: BlockNumber,
+        // 当前周期已经增发的数量
+        minted_in_period: Balance,
+        // 每个代币对应的份额数，rebase 时按新的总供应量重新计算
+        shares_per_token: u128,
+        // 所有账号份额的总和
+        total_shares: Balance,
+        // 合规检查合约，设置后每笔转账都需要通过它的 can_transfer 检查，None 表示不检查
+        compliance_registry: Option<AccountId>,
+        // 累计销毁的代币数量
+        total_burned: Balance,
+        // 不计入流通量的账号，比如国库和锁仓账号
+        non_circulating: StorageHashMap<AccountId, ()>,
+        // 是否允许转账，为 false 时代币不能转让（灵魂绑定），只能增发和销毁
+        transferable: bool,
+        // 代币的过期时间，过期后不能再转账，剩余的余额可以被任何人销毁，None 表示永不过期
+        expires_at: Option<Timestamp>,
     }
 
     // 定义事件，ink(topic) 标识有需要通过这个字段查询时间的需求
@@ -47,15 +149,273 @@ pub mod erc20 {
         value: Balance,
     }
 
+    #[ink(event)]
+    pub struct OperatorSet {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        operator: AccountId,
+        approved: bool,
+    }
+
+    // 增发和销毁单独定义事件，索引器不需要再通过 from 或者 to 为 None 的转账事件来推断
+    #[ink(event)]
+    pub struct Minted {
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Burned {
+        #[ink(topic)]
+        from: AccountId,
+        amount: Balance,
+    }
+
+    // 包装模式下，存入原生代币兑换本代币
+    #[ink(event)]
+    pub struct Deposit {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    // 包装模式下，销毁本代币取回原生代币
+    #[ink(event)]
+    pub struct Withdrawal {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    // 通过 merkle 证明领取了代币
+    #[ink(event)]
+    pub struct Claimed {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    // 跨链转入，由桥在目标链上增发
+    #[ink(event)]
+    pub struct BridgedIn {
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+        #[ink(topic)]
+        src_chain: u32,
+        nonce: u64,
+    }
+
+    // 跨链转出，由桥在本链上销毁
+    #[ink(event)]
+    pub struct BridgedOut {
+        #[ink(topic)]
+        from: AccountId,
+        amount: Balance,
+        #[ink(topic)]
+        dst_chain: u32,
+        nonce: u64,
+    }
+
+    // 创建者提交了一笔增发，eta 为可以执行的区块高度
+    #[ink(event)]
+    pub struct IssueQueued {
+        #[ink(topic)]
+        id: u64,
+        amount: Balance,
+        eta: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct IssueExecuted {
+        #[ink(topic)]
+        id: u64,
+    }
+
+    #[ink(event)]
+    pub struct IssueCancelled {
+        #[ink(topic)]
+        id: u64,
+    }
+
+    #[ink(event)]
+    pub struct IssueProposed {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        proposer: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct IssueConfirmed {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        signer: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct IssueProposalExecuted {
+        #[ink(topic)]
+        id: u64,
+    }
+
+    // 有人向持有人分发了原生代币分红
+    #[ink(event)]
+    pub struct DividendsDistributed {
+        #[ink(topic)]
+        from: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct DividendsClaimed {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    // 创建者放弃了权限，之后不能再增发
+    #[ink(event)]
+    pub struct CreaterRenounced {
+        #[ink(topic)]
+        previous: AccountId,
+    }
+
+    // 合规角色强制转移了账号的代币，与普通转账区分开以便审计
+    #[ink(event)]
+    pub struct Clawback {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+        #[ink(topic)]
+        operator: AccountId,
+    }
+
+    // 总供应量发生了 rebase
+    #[ink(event)]
+    pub struct Rebased {
+        total_supply: Balance,
+        shares_per_token: u128,
+    }
+
     // 定义不同错误的的枚举类型，
+    // 每个错误都有固定的编号（即 SCALE 编码的值），新增的错误只能追加在最后，不能修改已有的编号
+    // 派生 SCALE 编码时会把显式的编号转换成 u8，这里的转换是宏生成的
+    #[allow(clippy::unnecessary_cast)]
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub enum Error {
-        InsufficientBalance,
-        InsufficientAllowance,
-        OnlyForCreater,
+        InsufficientBalance = 0,
+        InsufficientAllowance = 1,
+        OnlyForCreater = 2,
+        NonZeroAllowance = 3,
+        TransferLimitExceeded = 4,
+        CooldownActive = 5,
+        RescueFailed = 6,
+        NotWrapped = 7,
+        NativeTransferFailed = 8,
+        TooManyRecipients = 9,
+        ClaimNotStarted = 10,
+        AlreadyClaimed = 11,
+        InvalidProof = 12,
+        ClaimExceedsTotal = 13,
+        MissingRole = 14,
+        NonceAlreadyUsed = 15,
+        UnknownIssue = 16,
+        TimelockNotExpired = 17,
+        DelayTooShort = 18,
+        MultisigRequired = 19,
+        SignersAlreadySet = 20,
+        InvalidThreshold = 21,
+        NotSigner = 22,
+        UnknownProposal = 23,
+        AlreadyConfirmed = 24,
+        NotEnoughConfirmations = 25,
+        ProposalAlreadyExecuted = 26,
+        SpenderNotAllowed = 27,
+        NoSupply = 28,
+        InvalidBps = 29,
+        InvalidPeriod = 30,
+        MintRateExceeded = 31,
+        IssuanceDisabled = 32,
+        ZeroAmount = 33,
+        SelfTransfer = 34,
+        InvalidRecipient = 35,
+        CapExceeded = 36,
+        ApprovalCallFailed = 37,
+        // rebase 后的总供应量为 0 或超出份额能表示的精度
+        InvalidRebase = 38,
+        // 合规检查合约拒绝了这笔转账
+        TransferNotCompliant = 39,
+        // 代币不允许转让
+        NonTransferable = 40,
+        // 代币已经过期
+        TokenExpired = 41,
+        // 代币还没有过期
+        NotExpired = 42,
+        // 包装模式下代币只能通过存入原生代币发行，并且始终与合约持有的原生代币 1:1 对应
+        WrappedSupply = 43,
+        // 分红数量太大，累计的每个份额分红超出了 u128 的范围
+        DividendOverflow = 44,
+    }
+
+    // 代币的基本信息，方便前端一次查询全部获取
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct TokenInfo {
+        pub name: String,
+        pub symbol: String,
+        pub decimals: u8,
+        pub total_supply: Balance,
+        pub creater: AccountId,
+    }
+
+    // 其他代币合约 transfer 消息的 selector，即 BLAKE2("transfer") 的前 4 个字节
+    const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+    // 接收授权回调的合约需要实现的 on_approval_received(owner, value, data) 消息的 selector
+    const ON_APPROVAL_RECEIVED_SELECTOR: [u8; 4] = [0xB9, 0x9F, 0xF6, 0x2D];
+    // 合规检查合约需要实现的 can_transfer(from, to, amount) -> bool 消息的 selector
+    const CAN_TRANSFER_SELECTOR: [u8; 4] = [0xDD, 0x54, 0x55, 0x1B];
+
+    // 角色标识
+    pub type RoleId = u32;
+    // 跨链桥角色，可以进行跨链增发和销毁
+    pub const BRIDGE: RoleId = 1;
+    // 合规角色，可以强制转移任意账号的代币
+    pub const COMPLIANCE: RoleId = 2;
+
+    // 默认的增发等待区块数，按 6 秒出块约为一天
+    const DEFAULT_ISSUE_DELAY: BlockNumber = 14_400;
+
+    // 多签增发提案
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct IssueProposal {
+        pub proposer: AccountId,
+        pub amount: Balance,
+        pub confirmations: u32,
+        pub executed: bool,
     }
 
+    // 分红累计值的放大倍数，避免每个份额分得的分红小于 1 时被舍去
+    // 每个代币初始对应 INITIAL_SHARES_PER_TOKEN 个份额，放大倍数相应地比按代币计算时大 10^6 倍
+    const DIVIDEND_MAGNITUDE: u128 = 1_000_000_000_000_000_000;
+
+    // 全 0 的占位账号，没有人持有这个账号的私钥，创建者放弃权限后设置为这个账号，也不能作为转账的接收者
+    const ZERO_ACCOUNT: [u8; 32] = [0x00; 32];
+
+    // 万分比的分母
+    const BPS_DENOMINATOR: Balance = 10_000;
+
+    // 初始时每个代币对应的份额数，份额越多，rebase 后余额的舍入误差越小
+    const INITIAL_SHARES_PER_TOKEN: u128 = 1_000_000;
+
     // 定义返回类型，当有返回值也可能返回错误的函数，需要用 Result 类型返回
     pub type Result<T> = core::result::Result<T, Error>;
 
@@ -63,23 +423,75 @@ pub mod erc20 {
         // 初始化部署代币
         // name : 代币名称，如 BitCoin
         // symbol : 代币标识，如 BTC
-        // total_subbly : 总供应量
+        // total_subbly : 总供应量，余额按 INITIAL_SHARES_PER_TOKEN 倍的份额记账，
+        // 总供应量不能超过 u128::MAX / INITIAL_SHARES_PER_TOKEN（约 3.4e32 个最小单位），否则部署失败
+        // name 和 symbol 为 UTF-8 字符串，SCALE 编码与之前的 Vec<u8> 相同（长度前缀 + 字节），
+        // 已有的调用方式不受影响，Polkadot JS 会将其显示为文本
         #[ink(constructor)]
-        pub fn new(name: Vec<u8>, symbol: Vec<u8>, total_supply: Balance) -> Self {
+        pub fn new(name: String, symbol: String, total_supply: Balance) -> Self {
             // 获取部署的调用者
             let caller = Self::env().caller();
-            // 定义余额数据，将所有发行的代币，都放给部署账号
-            let mut balances = StorageHashMap::new();
-            balances.insert(caller, total_supply);
             // 定义数据存储
-            let instance = Self {
+            let mut instance = Self {
                 creater : caller,
                 name: name,
                 symbol: symbol,
+                // 默认使用 18 位精度
+                decimals: 18,
                 total_supply: total_supply,
-                balances: balances,
-                allowances: StorageHashMap::new()
+                shares: LazyHashMap::new(),
+                allowances: LazyHashMap::new(),
+                strict_approvals: false,
+                max_transfer_amount: None,
+                transfer_limit_exempt: StorageHashMap::new(),
+                cooldown_blocks: 0,
+                last_transfer_block: StorageHashMap::new(),
+                holders: StorageVec::new(),
+                holder_index: StorageHashMap::new(),
+                holders_added: 0,
+                holders_removed: 0,
+                wrapped: false,
+                max_airdrop_recipients: 100,
+                claim_root: None,
+                claim_total: 0,
+                claimed_amount: 0,
+                claimed_leaves: StorageHashMap::new(),
+                roles: StorageHashMap::new(),
+                bridge_in_nonces: StorageHashMap::new(),
+                bridge_out_nonce: 0,
+                issue_delay: DEFAULT_ISSUE_DELAY,
+                queued_issues: StorageHashMap::new(),
+                next_issue_id: 0,
+                signers: StorageVec::new(),
+                threshold: 0,
+                issue_proposals: StorageHashMap::new(),
+                proposal_confirmations: StorageHashMap::new(),
+                next_proposal_id: 0,
+                operators: StorageHashMap::new(),
+                spender_allowlist_enabled: false,
+                allowed_spenders: StorageHashMap::new(),
+                nonces: StorageHashMap::new(),
+                dividend_per_share: 0,
+                dividend_index_of: StorageHashMap::new(),
+                dividends_owed: StorageHashMap::new(),
+                burn_bps: 0,
+                cap: None,
+                max_mint_per_period: None,
+                period_blocks: 0,
+                mint_period: 0,
+                minted_in_period: 0,
+                shares_per_token: INITIAL_SHARES_PER_TOKEN,
+                total_shares: total_supply
+                    .checked_mul(INITIAL_SHARES_PER_TOKEN)
+                    .expect("total_supply exceeds u128::MAX / INITIAL_SHARES_PER_TOKEN"),
+                compliance_registry: None,
+                total_burned: 0,
+                non_circulating: StorageHashMap::new(),
+                transferable: true,
+                expires_at: None,
             };
+            // 定义余额数据，将所有发行的代币，都放给部署账号
+            instance.set_shares(caller, instance.total_shares);
             // 触发转账事件，因为第一笔发行，也是一种转账
             Self::env().emit_event(Transfer {
                 from: None,
@@ -90,31 +502,145 @@ pub mod erc20 {
             instance
         }
 
-        // 返回代币名称，如 BitCoin
+        // 以包装原生代币模式部署，初始供应量为 0，代币只能通过 deposit 存入原生代币获得
+        #[ink(constructor)]
+        pub fn new_wrapped(name: String, symbol: String) -> Self {
+            let mut instance = Self::new(name, symbol, 0);
+            instance.wrapped = true;
+            instance
+        }
+
+        // 可以指定代币是否允许转让的构造函数，transferable 为 false 时代币不能转让，适合用作积分或声誉
+        #[ink(constructor)]
+        pub fn new_with_transferable(name: String, symbol: String, total_supply: Balance, transferable: bool) -> Self {
+            let mut instance = Self::new(name, symbol, total_supply);
+            instance.transferable = transferable;
+            instance
+        }
+
+        // 返回代币是否允许转让
+        #[ink(message)]
+        pub fn transferable(&self) -> bool {
+            self.transferable
+        }
+
+        // 返回代币名称，如 BitCoin，SCALE 编码与 Vec<u8> 相同
         #[ink(message)]
-        pub fn name(&self) -> Vec<u8>{
+        pub fn name(&self) -> String{
             self.name.clone()
         }
 
-        // 返回代币标识，比如 BTC
+        // 返回代币标识，比如 BTC，SCALE 编码与 Vec<u8> 相同
         #[ink(message)]
-        pub fn symbol(&self) -> Vec<u8>{
+        pub fn symbol(&self) -> String{
             self.symbol.clone()
         }
 
+        // 返回代币精度
+        #[ink(message)]
+        pub fn decimals(&self) -> u8{
+            self.decimals
+        }
+
         // 返回代币总供应量
         #[ink(message)]
         pub fn total_supply(&self) -> Balance{
             self.total_supply
         }
         
+        // 一次返回代币的名称、标识、精度、总供应量和创建者
+        #[ink(message)]
+        pub fn token_info(&self) -> TokenInfo{
+            TokenInfo {
+                name: self.name.clone(),
+                symbol: self.symbol.clone(),
+                decimals: self.decimals,
+                total_supply: self.total_supply,
+                creater: self.creater,
+            }
+        }
+
+        // 分页返回持有人及其余额，offset 为起始位置，limit 为最多返回的数量
+        #[ink(message)]
+        pub fn holders(&self, offset: u32, limit: u32) -> Vec<(AccountId, Balance)>{
+            self.holders
+                .iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .map(|holder| (*holder, self.balance_of(*holder)))
+                .collect()
+        }
+
+        // 返回当前的持有人数量
+        #[ink(message)]
+        pub fn holders_count(&self) -> u32{
+            self.holders.len()
+        }
+
+        // 返回余额从 0 变为非 0 的累计次数
+        #[ink(message)]
+        pub fn holders_added(&self) -> u64{
+            self.holders_added
+        }
+
+        // 返回余额从非 0 变为 0 的累计次数
+        #[ink(message)]
+        pub fn holders_removed(&self) -> u64{
+            self.holders_removed
+        }
+
         // 返回指定账号的余额
         #[ink(message)]
         pub fn balance_of(&self, of: AccountId) -> Balance{
-            // 返回的值是 &Balance 的类型，所以需要 * 解引用
-            // 可以使用 copied ，这样就不需要解引用了
-            let balance = self.balances.get(&of).unwrap_or(&0);
-            *balance
+            self.shares_of(of) / self.shares_per_token
+        }
+
+        // 返回指定账号持有的份额
+        #[ink(message)]
+        pub fn shares_of(&self, of: AccountId) -> Balance{
+            // 返回的值是 Option<&Balance> 的类型，使用 copied 后就不需要解引用了
+            self.shares.get(&of).copied().unwrap_or(0)
+        }
+
+        // 返回每个代币对应的份额数
+        #[ink(message)]
+        pub fn shares_per_token(&self) -> u128{
+            self.shares_per_token
+        }
+
+        // 只有创建者可以 rebase，按 delta 调整总供应量，所有账号的余额按比例同时变化
+        // 账号持有的份额不变，只重新计算每个代币对应的份额数
+        #[ink(message)]
+        pub fn rebase(&mut self, delta: i128) -> Result<()>{
+            let caller = self.env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            // 包装模式下总供应量必须等于存入的原生代币
+            if self.wrapped {
+                return Err(Error::WrappedSupply)
+            }
+            let new_supply = if delta >= 0 {
+                self.total_supply.checked_add(delta as Balance)
+            } else {
+                self.total_supply.checked_sub(delta.unsigned_abs())
+            };
+            let new_supply = match new_supply {
+                Some(supply) if supply > 0 => supply,
+                _ => return Err(Error::InvalidRebase),
+            };
+            let shares_per_token = self.total_shares / new_supply;
+            if shares_per_token == 0 {
+                return Err(Error::InvalidRebase)
+            }
+            self.ensure_cap(new_supply.saturating_sub(self.total_supply))?;
+            self.total_supply = new_supply;
+            self.shares_per_token = shares_per_token;
+            self.env().emit_event( Rebased{
+                total_supply : new_supply,
+                shares_per_token,
+            });
+            Ok(())
         }
         
         // // 向指定账号转账
@@ -122,61 +648,305 @@ pub mod erc20 {
         pub fn transfer(&mut self, to: AccountId, value:Balance) -> Result<()>{
             // 获取调用者
             let caller = Self::env().caller();
+            self.ensure_transfer_allowed(caller, to, value)?;
 
-            self.transfer_from_to(Some(caller), Some(to), value)
+            self.transfer_with_burn(caller, to, value)?;
+            self.record_transfer(caller);
+            Ok(())
         }
 
         // 授权某账号可以使用自己的账户余额
         #[ink(message)]
         pub fn approve(&mut self, spender: AccountId, value: Balance) -> Result<()>{
             let caller = Self::env().caller();
-            // 插入授权的记录，授权是未来花费，所以不需要考虑当前是否有余额是否足够，
-            self.allowances.insert((caller, spender), value);
+            self.approve_from_to(caller, spender, value, None)
+        }
 
-            self.env().emit_event( Approval{
+        // 授权某账号可以使用自己的账户余额，到达 expires_at 时间后授权失效
+        #[ink(message)]
+        pub fn approve_with_expiry(&mut self, spender: AccountId, value: Balance, expires_at: Timestamp) -> Result<()>{
+            let caller = Self::env().caller();
+            self.approve_from_to(caller, spender, value, Some(expires_at))
+        }
+
+        // 授权后立即调用 spender 合约的 on_approval_received(owner, value, data)，
+        // 一笔交易完成"授权 + 存入"之类的操作，回调失败时恢复原来的授权
+        #[ink(message)]
+        pub fn approve_and_call(&mut self, spender: AccountId, value: Balance, data: Vec<u8>) -> Result<()>{
+            let caller = Self::env().caller();
+            let previous = self.allowances.get(&(caller, spender)).copied();
+            self.approve_from_to(caller, spender, value, None)?;
+
+            let result = build_call::<Environment>()
+                .callee(spender)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ON_APPROVAL_RECEIVED_SELECTOR))
+                        .push_arg(caller)
+                        .push_arg(value)
+                        .push_arg(data)
+                )
+                .returns::<()>()
+                .fire();
+            if result.is_err() {
+                self.allowances.put((caller, spender), previous);
+                self.env().emit_event( Approval{
+                    owner : caller,
+                    spender,
+                    value : previous.map(|(value, _)| value).unwrap_or(0),
+                });
+                return Err(Error::ApprovalCallFailed)
+            }
+            Ok(())
+        }
+
+        // 获取第一个账户授权第二个账户可使用的数量，已过期的授权视为 0
+        #[ink(message)]
+        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            match self.allowances.get(&(owner, spender)) {
+                Some((_, Some(expires_at))) if self.env().block_timestamp() >= *expires_at => 0,
+                Some((value, _)) => *value,
+                None => 0,
+            }
+        }
+        
+        // 返回账号当前的签名 nonce，链下签名时需要带上这个值
+        #[ink(message)]
+        pub fn nonces(&self, owner: AccountId) -> u64 {
+            self.nonces.get(&owner).copied().unwrap_or(0)
+        }
+
+        // 设置或取消调用者的操作员，操作员可以不受授权额度限制地调用 transfer_from 转走调用者的代币
+        #[ink(message)]
+        pub fn set_operator(&mut self, operator: AccountId, approved: bool) -> Result<()>{
+            let caller = Self::env().caller();
+            if approved {
+                if self.spender_allowlist_enabled && !self.allowed_spenders.contains_key(&operator) {
+                    return Err(Error::SpenderNotAllowed)
+                }
+                self.operators.insert((caller, operator), ());
+            } else {
+                self.operators.take(&(caller, operator));
+            }
+
+            self.env().emit_event( OperatorSet{
                 owner : caller,
-                spender : spender,
-                value : value,
+                operator,
+                approved,
             });
             Ok(())
         }
 
-        // 获取第一个账户授权第二个账户可使用的数量
+        // 返回 operator 是否为 owner 的操作员
         #[ink(message)]
-        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
-            // 或者 self.allowances.get(&(owner, spender)).copied().unwrap_or(0)
-            // 因为 copied 后，就不需要解引用(*)了
-            *self.allowances.get(&(owner, spender)).unwrap_or(&0)
+        pub fn is_operator(&self, owner: AccountId, operator: AccountId) -> bool {
+            self.operators.contains_key(&(owner, operator))
+        }
+
+        // 一次返回 owner 授权给多个账号的数量，顺序与 spenders 一致
+        #[ink(message)]
+        pub fn allowances_of(&self, owner: AccountId, spenders: Vec<AccountId>) -> Vec<Balance> {
+            spenders
+                .into_iter()
+                .map(|spender| self.allowance(owner, spender))
+                .collect()
         }
         
         // 在授权(allowance)范围内，将指定账号的代币转到指定账号
         #[ink(message)]
         pub fn transfer_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<()>{
             let caller = Self::env().caller();
+            // 操作员可以转走 from 的任意数量，不需要也不扣减授权额度
+            let is_operator = self.is_operator(from, caller);
             let allowance = self.allowance(from, caller);
-            if allowance < value {
+            if !is_operator && allowance < value {
                 return Err(Error::InsufficientAllowance)
             }
-            self.transfer_from_to(Some(from), Some(to) , value)?;
+            self.ensure_transfer_allowed(from, to, value)?;
+            self.transfer_with_burn(from, to, value)?;
+            self.record_transfer(from);
 
-            self.allowances.insert((from, to), allowance - value);
+            // 扣减的是 from 授权给调用者的额度
+            if !is_operator {
+                self.set_remaining_allowance(from, caller, allowance - value);
+            }
             
             Ok(())
         }
 
+        // 内部函数，修改剩余的授权额度，过期时间保持不变
+        fn set_remaining_allowance(&mut self, owner: AccountId, spender: AccountId, remaining: Balance) {
+            if let Some((value, _)) = self.allowances.get_mut(&(owner, spender)) {
+                *value = remaining;
+            }
+        }
+
+        // 内部函数，插入授权的记录，授权是未来花费，所以不需要考虑当前是否有余额是否足够
+        fn approve_from_to(&mut self, owner: AccountId, spender: AccountId, value: Balance, expires_at: Option<Timestamp>) -> Result<()>{
+            // 严格授权模式下，只能从 0 授权到非 0，或者将授权清零，防止抢跑同时花掉新旧两笔额度
+            if self.strict_approvals && value != 0 && self.allowance(owner, spender) != 0 {
+                return Err(Error::NonZeroAllowance)
+            }
+            // 开启白名单后只能授权给白名单中的账号，撤销授权（授权为 0）不受限制
+            if self.spender_allowlist_enabled && value != 0 && !self.allowed_spenders.contains_key(&spender) {
+                return Err(Error::SpenderNotAllowed)
+            }
+            // 授权清零时直接删除记录，释放存储
+            let allowance = if value == 0 { None } else { Some((value, expires_at)) };
+            self.allowances.put((owner, spender), allowance);
+
+            self.env().emit_event( Approval{
+                owner,
+                spender,
+                value,
+            });
+            Ok(())
+        }
+
+        // 内部函数，检查普通转账（transfer 和 transfer_from）是否满足限制条件
+        fn ensure_transfer_allowed(&self, from: AccountId, to: AccountId, value: Balance) -> Result<()>{
+            if !self.transferable {
+                return Err(Error::NonTransferable)
+            }
+            if self.is_expired() {
+                return Err(Error::TokenExpired)
+            }
+            if value == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            if from == to {
+                return Err(Error::SelfTransfer)
+            }
+            // 不能转给占位账号，转过去的代币将永远无法取回
+            if to == AccountId::from(ZERO_ACCOUNT) {
+                return Err(Error::InvalidRecipient)
+            }
+            // 创建者和白名单账号不受单笔转账上限的限制
+            if let Some(max_transfer_amount) = self.max_transfer_amount {
+                let exempt = from == self.creater || self.transfer_limit_exempt.contains_key(&from);
+                if !exempt && value > max_transfer_amount {
+                    return Err(Error::TransferLimitExceeded)
+                }
+            }
+            // 冷却期内不能再次转出
+            if self.cooldown_blocks > 0 {
+                if let Some(last_block) = self.last_transfer_block.get(&from) {
+                    if self.env().block_number() < last_block + self.cooldown_blocks {
+                        return Err(Error::CooldownActive)
+                    }
+                }
+            }
+            // 设置了合规检查合约时，跨合约调用它的 can_transfer，调用失败也视为不合规
+            if let Some(registry) = self.compliance_registry {
+                let result = build_call::<Environment>()
+                    .callee(registry)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(CAN_TRANSFER_SELECTOR))
+                            .push_arg(from)
+                            .push_arg(to)
+                            .push_arg(value)
+                    )
+                    .returns::<ReturnType<bool>>()
+                    .fire();
+                if !matches!(result, Ok(true)) {
+                    return Err(Error::TransferNotCompliant)
+                }
+            }
+            Ok(())
+        }
+
+        // 内部函数，记录账号最后一次转出的区块高度，用于计算冷却期
+        fn record_transfer(&mut self, from: AccountId) {
+            if self.cooldown_blocks > 0 {
+                let block_number = self.env().block_number();
+                self.last_transfer_block.insert(from, block_number);
+            }
+        }
+
+        // 内部函数，写入账号份额，份额为 0 时删除记录，释放存储
+        // 同时维护持有人列表：份额从 0 变为非 0 时加入，变为 0 时移除
+        // 份额变化前先按旧余额结算分红
+        fn set_shares(&mut self, account: AccountId, shares: Balance) {
+            self.settle_dividends(account);
+            let is_holder = self.holder_index.contains_key(&account);
+            if shares == 0 {
+                self.shares.put(account, None);
+                if is_holder {
+                    self.remove_holder(account);
+                }
+            } else {
+                self.shares.put(account, Some(shares));
+                if !is_holder {
+                    self.holder_index.insert(account, self.holders.len());
+                    self.holders.push(account);
+                    self.holders_added += 1;
+                }
+            }
+        }
+
+        // 内部函数，按账号当前余额结算上次结算以来新增的分红
+        fn settle_dividends(&mut self, account: AccountId) {
+            let pending = self.pending_dividends(account);
+            if pending > 0 {
+                let owed = self.dividends_owed.get(&account).copied().unwrap_or(0);
+                self.dividends_owed.insert(account, owed + pending);
+            }
+            self.dividend_index_of.insert(account, self.dividend_per_share);
+        }
+
+        // 内部函数，计算账号上次结算以来新增的分红，按份额计算
+        fn pending_dividends(&self, account: AccountId) -> Balance {
+            let last_index = self.dividend_index_of.get(&account).copied().unwrap_or(0);
+            // 份额乘以累计分红可能超出 u128，用 256 位的中间结果计算，结果不会超过分红总额
+            mul_div(self.shares_of(account), self.dividend_per_share - last_index, DIVIDEND_MAGNITUDE)
+                .unwrap_or(Balance::MAX)
+        }
+
+        // 内部函数，从持有人列表中移除账号，把最后一个持有人移到空出来的位置
+        fn remove_holder(&mut self, account: AccountId) {
+            if let Some(index) = self.holder_index.take(&account) {
+                self.holders.swap_remove_drop(index);
+                if let Some(moved) = self.holders.get(index) {
+                    self.holder_index.insert(*moved, index);
+                }
+                self.holders_removed += 1;
+            }
+        }
+
+        // 内部函数，普通转账时按 burn_bps 销毁一部分，剩余部分转给接收者
+        fn transfer_with_burn(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<()>{
+            // 先检查余额，避免转账成功后销毁失败
+            if self.balance_of(from) < value {
+                return Err(Error::InsufficientBalance)
+            }
+            let burn_amount = value * self.burn_bps as Balance / BPS_DENOMINATOR;
+            self.transfer_from_to(Some(from), Some(to), value - burn_amount)?;
+            if burn_amount > 0 {
+                self.burn_from_account(from, burn_amount)?;
+            }
+            Ok(())
+        }
+
         // 内部函数，用于从一个账户转账到另外一个账户
         fn transfer_from_to(&mut self, from: Option<AccountId>, to: Option<AccountId>, value:Balance) -> Result<()>{
+            // 转账按份额记账，转出的份额 = 金额 * 每个代币对应的份额数
+            let value_shares = value * self.shares_per_token;
             // 判断 from 账户是否有足够多的钱
             if let Some(from_account) = from {
-                let from_balance = self.balance_of(from_account);
-                if from_balance < value {
+                if self.balance_of(from_account) < value {
                     return Err(Error::InsufficientBalance)
                 }
-                self.balances.insert(from_account, from_balance - value);
+                let from_shares = self.shares_of(from_account);
+                self.set_shares(from_account, from_shares - value_shares);
+            } else {
+                // 增发时总份额增加
+                self.total_shares += value_shares;
             }
             if let Some(to_account) = to {
-                let to_balance = self.balance_of(to_account);
-                self.balances.insert(to_account, to_balance + value);
+                let to_shares = self.shares_of(to_account);
+                self.set_shares(to_account, to_shares + value_shares);
+            } else {
+                // 销毁时总份额减少
+                self.total_shares -= value_shares;
             }
             
             self.env().emit_event( Transfer{
@@ -187,33 +957,994 @@ pub mod erc20 {
             Ok(())
         }
 
-        // 增发代币，只能创建者可以增发，增发的会直接转账给创建者，增发需要增加总供应量
+        // 提交一笔增发，需要等待 issue_delay 个区块后才能执行，持有人可以提前看到增发计划
+        // 只能创建者可以增发，返回排队增发的 id
         #[ink(message)]
-        pub fn issue(&mut self, amount: Balance) -> Result<()>{
+        pub fn queue_issue(&mut self, amount: Balance) -> Result<u64>{
             let caller = Self::env().caller();
             if caller != self.creater {
                 return Err(Error::OnlyForCreater)
             }
-            let total_supply = self.total_supply();
-            self.total_supply = total_supply + amount;
+            // 设置了多签之后，创建者不能再单独增发
+            if self.threshold > 0 {
+                return Err(Error::MultisigRequired)
+            }
+            if self.wrapped {
+                return Err(Error::WrappedSupply)
+            }
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let id = self.next_issue_id;
+            self.next_issue_id = id + 1;
+            let eta = self.env().block_number() + self.issue_delay;
+            self.queued_issues.insert(id, (amount, eta));
+
+            self.env().emit_event( IssueQueued{
+                id,
+                amount,
+                eta,
+            });
+            Ok(id)
+        }
+
+        // 执行到期的增发，增发的会直接转账给创建者，增发需要增加总供应量
+        #[ink(message)]
+        pub fn execute_issue(&mut self, id: u64) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            if self.threshold > 0 {
+                return Err(Error::MultisigRequired)
+            }
+            let (amount, eta) = *self.queued_issues.get(&id).ok_or(Error::UnknownIssue)?;
+            if self.env().block_number() < eta {
+                return Err(Error::TimelockNotExpired)
+            }
+            self.ensure_cap(amount)?;
+            self.mint_to(caller, amount)?;
+            self.queued_issues.take(&id);
 
-            self.transfer_from_to(None, Some(caller) , amount)?;
+            self.env().emit_event( IssueExecuted{ id });
             Ok(())
         }
 
-        // 销毁代币，任何账号都可以销毁自己持有的代币，销毁后需要减少总供应量
+        // 取消排队中的增发
         #[ink(message)]
-        pub fn burn(&mut self, amount: Balance) -> Result<()>{
+        pub fn cancel_issue(&mut self, id: u64) -> Result<()>{
             let caller = Self::env().caller();
-            self.transfer_from_to(Some(caller), None, amount)?;
-            let total_supply = self.total_supply();
-            self.total_supply = total_supply - amount;
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.queued_issues.take(&id).ok_or(Error::UnknownIssue)?;
 
+            self.env().emit_event( IssueCancelled{ id });
             Ok(())
         }
-    }
 
-    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+        // 返回排队中的增发数量和可以执行的区块高度
+        #[ink(message)]
+        pub fn queued_issue(&self, id: u64) -> Option<(Balance, BlockNumber)> {
+            self.queued_issues.get(&id).copied()
+        }
+
+        // 返回增发需要等待的区块数
+        #[ink(message)]
+        pub fn issue_delay(&self) -> BlockNumber {
+            self.issue_delay
+        }
+
+        // 修改增发需要等待的区块数，只能延长不能缩短，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_issue_delay(&mut self, delay: BlockNumber) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            if delay < self.issue_delay {
+                return Err(Error::DelayTooShort)
+            }
+            self.issue_delay = delay;
+            Ok(())
+        }
+
+        // 设置多签增发的签名人和确认数，只能设置一次，设置后增发只能通过多签提案进行
+        // 只能创建者可以设置
+        #[ink(message)]
+        pub fn set_signers(&mut self, signers: Vec<AccountId>, threshold: u32) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            if self.threshold > 0 {
+                return Err(Error::SignersAlreadySet)
+            }
+            // 去掉重复的签名人后再检查确认数
+            let mut unique = Vec::new();
+            for signer in signers {
+                if !unique.contains(&signer) {
+                    unique.push(signer);
+                }
+            }
+            if threshold == 0 || threshold as usize > unique.len() {
+                return Err(Error::InvalidThreshold)
+            }
+            for signer in unique {
+                self.signers.push(signer);
+            }
+            self.threshold = threshold;
+            Ok(())
+        }
+
+        // 返回多签增发的签名人和确认数
+        #[ink(message)]
+        pub fn signers(&self) -> (Vec<AccountId>, u32) {
+            (self.signers.iter().copied().collect(), self.threshold)
+        }
+
+        // 内部函数，判断账号是否为多签签名人
+        fn is_signer(&self, account: AccountId) -> bool {
+            self.signers.iter().any(|signer| *signer == account)
+        }
+
+        // 签名人发起多签增发提案，发起人自动确认，返回提案 id
+        #[ink(message)]
+        pub fn propose_issue(&mut self, amount: Balance) -> Result<u64>{
+            let caller = Self::env().caller();
+            if !self.is_signer(caller) {
+                return Err(Error::NotSigner)
+            }
+            if self.wrapped {
+                return Err(Error::WrappedSupply)
+            }
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let id = self.next_proposal_id;
+            self.next_proposal_id = id + 1;
+            self.issue_proposals.insert(id, IssueProposal {
+                proposer: caller,
+                amount,
+                confirmations: 0,
+                executed: false,
+            });
+            self.env().emit_event( IssueProposed{
+                id,
+                proposer : caller,
+                amount,
+            });
+
+            self.confirm(id)?;
+            Ok(id)
+        }
+
+        // 签名人确认多签增发提案，每个签名人只能确认一次
+        #[ink(message)]
+        pub fn confirm(&mut self, id: u64) -> Result<()>{
+            let caller = Self::env().caller();
+            if !self.is_signer(caller) {
+                return Err(Error::NotSigner)
+            }
+            if self.proposal_confirmations.contains_key(&(id, caller)) {
+                return Err(Error::AlreadyConfirmed)
+            }
+            let proposal = self.issue_proposals.get_mut(&id).ok_or(Error::UnknownProposal)?;
+            if proposal.executed {
+                return Err(Error::ProposalAlreadyExecuted)
+            }
+            proposal.confirmations += 1;
+            self.proposal_confirmations.insert((id, caller), ());
+
+            self.env().emit_event( IssueConfirmed{
+                id,
+                signer : caller,
+            });
+            Ok(())
+        }
+
+        // 确认数达到要求后，签名人执行多签增发提案，增发的代币转给创建者
+        #[ink(message)]
+        pub fn execute(&mut self, id: u64) -> Result<()>{
+            let caller = Self::env().caller();
+            if !self.is_signer(caller) {
+                return Err(Error::NotSigner)
+            }
+            let mut proposal = self.issue_proposals.get(&id).cloned().ok_or(Error::UnknownProposal)?;
+            if proposal.executed {
+                return Err(Error::ProposalAlreadyExecuted)
+            }
+            if proposal.confirmations < self.threshold {
+                return Err(Error::NotEnoughConfirmations)
+            }
+            if self.is_renounced() {
+                return Err(Error::IssuanceDisabled)
+            }
+            self.ensure_cap(proposal.amount)?;
+            let creater = self.creater;
+            self.mint_to(creater, proposal.amount)?;
+            proposal.executed = true;
+            self.issue_proposals.insert(id, proposal);
+
+            self.env().emit_event( IssueProposalExecuted{ id });
+            Ok(())
+        }
+
+        // 分页返回多签增发提案，按 id 从 offset 开始
+        #[ink(message)]
+        pub fn issue_proposals(&self, offset: u64, limit: u64) -> Vec<(u64, IssueProposal)> {
+            (offset..self.next_proposal_id)
+                .take(limit as usize)
+                .filter_map(|id| self.issue_proposals.get(&id).map(|proposal| (id, proposal.clone())))
+                .collect()
+        }
+
+        // 返回签名人是否确认了提案
+        #[ink(message)]
+        pub fn is_confirmed_by(&self, id: u64, signer: AccountId) -> bool {
+            self.proposal_confirmations.contains_key(&(id, signer))
+        }
+
+        // 创建者永久放弃权限，创建者被设置为占位账号，之后所有增发和创建者才能调用的设置都无法再执行，
+        // 放弃前授予的跨链桥角色和设置的空投也不能再增发，只有包装模式下按 1:1 存入原生代币的发行不受影响
+        #[ink(message)]
+        pub fn renounce_creater(&mut self) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.creater = AccountId::from(ZERO_ACCOUNT);
+
+            self.env().emit_event( CreaterRenounced{ previous : caller });
+            Ok(())
+        }
+
+        // 返回创建者是否已经放弃权限
+        #[ink(message)]
+        pub fn is_renounced(&self) -> bool {
+            self.creater == AccountId::from(ZERO_ACCOUNT)
+        }
+
+        // 空投，给列表中的每个账号增发指定数量的代币，只能创建者可以调用
+        // 每个接收账号都会触发各自的转账和增发事件
+        #[ink(message)]
+        pub fn airdrop(&mut self, recipients: Vec<(AccountId, Balance)>) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            if recipients.len() > self.max_airdrop_recipients as usize {
+                return Err(Error::TooManyRecipients)
+            }
+            let total: Balance = recipients.iter().map(|(_, amount)| *amount).sum();
+            self.ensure_cap(total)?;
+            // 增发额度在每次增发时扣减，这里先确认额度足够整个列表，避免只空投了一部分
+            self.ensure_mint_budget(total)?;
+            for (to, amount) in recipients {
+                self.mint_to(to, amount)?;
+            }
+            Ok(())
+        }
+
+        // 返回单次空投最多可以包含的接收账号数量
+        #[ink(message)]
+        pub fn max_airdrop_recipients(&self) -> u32 {
+            self.max_airdrop_recipients
+        }
+
+        // 设置单次空投最多可以包含的接收账号数量，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_max_airdrop_recipients(&mut self, max: u32) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.max_airdrop_recipients = max;
+            Ok(())
+        }
+
+        // 设置领取空投的 merkle 树根和可领取的总量，已领取的数量重新从 0 开始计算，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_claim_root(&mut self, root: Hash, total: Balance) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.claim_root = Some(root);
+            self.claim_total = total;
+            self.claimed_amount = 0;
+            Ok(())
+        }
+
+        // 返回领取空投的 merkle 树根
+        #[ink(message)]
+        pub fn claim_root(&self) -> Option<Hash> {
+            self.claim_root
+        }
+
+        // 返回可领取的总量和已经领取的数量
+        #[ink(message)]
+        pub fn claim_progress(&self) -> (Balance, Balance) {
+            (self.claim_total, self.claimed_amount)
+        }
+
+        // 凭 merkle 证明领取空投，证明的叶子为 (调用者, 数量)，每个叶子只能领取一次
+        #[ink(message)]
+        pub fn claim(&mut self, amount: Balance, proof: Vec<Hash>) -> Result<()>{
+            if self.is_renounced() {
+                return Err(Error::IssuanceDisabled)
+            }
+            let caller = Self::env().caller();
+            let root = self.claim_root.ok_or(Error::ClaimNotStarted)?;
+
+            let mut leaf = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_encoded::<Blake2x256, _>(&(caller, amount), &mut leaf);
+            let leaf = Hash::from(leaf);
+            if self.claimed_leaves.contains_key(&leaf) {
+                return Err(Error::AlreadyClaimed)
+            }
+            if Self::merkle_root(leaf, &proof) != root {
+                return Err(Error::InvalidProof)
+            }
+            if self.claimed_amount + amount > self.claim_total {
+                return Err(Error::ClaimExceedsTotal)
+            }
+            self.ensure_cap(amount)?;
+
+            self.mint_to(caller, amount)?;
+            self.claimed_leaves.insert(leaf, ());
+            self.claimed_amount += amount;
+            self.env().emit_event( Claimed{
+                account : caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 内部函数，根据叶子和证明计算 merkle 树根，每一层把较小的哈希放在前面拼接后再哈希
+        fn merkle_root(leaf: Hash, proof: &[Hash]) -> Hash {
+            proof.iter().fold(leaf, |computed, sibling| {
+                let (first, second) = if computed <= *sibling {
+                    (computed, *sibling)
+                } else {
+                    (*sibling, computed)
+                };
+                let mut input = [0u8; 64];
+                input[..32].copy_from_slice(first.as_ref());
+                input[32..].copy_from_slice(second.as_ref());
+                let mut output = <Blake2x256 as HashOutput>::Type::default();
+                ink_env::hash_bytes::<Blake2x256>(&input, &mut output);
+                Hash::from(output)
+            })
+        }
+
+        // 返回总供应量上限
+        #[ink(message)]
+        pub fn cap(&self) -> Option<Balance> {
+            self.cap
+        }
+
+        // 设置总供应量上限，None 表示不限制，不能低于当前的总供应量，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_cap(&mut self, cap: Option<Balance>) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            if let Some(cap) = cap {
+                if cap < self.total_supply {
+                    return Err(Error::CapExceeded)
+                }
+            }
+            self.cap = cap;
+            Ok(())
+        }
+
+        // 内部函数，检查增发后总供应量是否会超过上限
+        fn ensure_cap(&self, amount: Balance) -> Result<()>{
+            if let Some(cap) = self.cap {
+                if self.total_supply + amount > cap {
+                    return Err(Error::CapExceeded)
+                }
+            }
+            Ok(())
+        }
+
+        // 返回每个周期最多可以增发的数量和周期长度
+        #[ink(message)]
+        pub fn mint_rate_limit(&self) -> (Option<Balance>, BlockNumber) {
+            (self.max_mint_per_period, self.period_blocks)
+        }
+
+        // 返回当前周期已经增发的数量
+        #[ink(message)]
+        pub fn minted_in_current_period(&self) -> Balance {
+            if self.period_blocks == 0 || self.env().block_number() / self.period_blocks != self.mint_period {
+                return 0
+            }
+            self.minted_in_period
+        }
+
+        // 设置每个周期最多可以增发的数量和周期长度，None 表示不限制，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_mint_rate_limit(&mut self, max_per_period: Option<Balance>, period_blocks: BlockNumber) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            if max_per_period.is_some() && period_blocks == 0 {
+                return Err(Error::InvalidPeriod)
+            }
+            self.max_mint_per_period = max_per_period;
+            self.period_blocks = period_blocks;
+            self.mint_period = 0;
+            self.minted_in_period = 0;
+            Ok(())
+        }
+
+        // 内部函数，确认当前周期剩余的增发额度足够增发 amount
+        fn ensure_mint_budget(&self, amount: Balance) -> Result<()>{
+            match self.max_mint_per_period {
+                Some(max_per_period) if self.minted_in_current_period() + amount > max_per_period => {
+                    Err(Error::MintRateExceeded)
+                }
+                _ => Ok(()),
+            }
+        }
+
+        // 内部函数，扣减当前周期的增发额度，进入新周期时额度重新计算
+        fn consume_mint_budget(&mut self, amount: Balance) -> Result<()>{
+            if self.max_mint_per_period.is_none() {
+                return Ok(())
+            }
+            self.ensure_mint_budget(amount)?;
+            let minted = self.minted_in_current_period();
+            self.mint_period = self.env().block_number() / self.period_blocks;
+            self.minted_in_period = minted + amount;
+            Ok(())
+        }
+
+        // 内部函数，增发代币给指定账号，包装模式下只能通过 deposit 发行，其他增发一律拒绝
+        fn mint_to(&mut self, to: AccountId, amount: Balance) -> Result<()>{
+            if self.wrapped {
+                return Err(Error::WrappedSupply)
+            }
+            self.issue_to(to, amount)
+        }
+
+        // 内部函数，发行代币给指定账号，增发需要增加总供应量，所有发行都在这里扣减当前周期的增发额度
+        fn issue_to(&mut self, to: AccountId, amount: Balance) -> Result<()>{
+            self.consume_mint_budget(amount)?;
+            let total_supply = self.total_supply();
+            self.total_supply = total_supply + amount;
+
+            self.transfer_from_to(None, Some(to) , amount)?;
+            self.env().emit_event( Minted{
+                to,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 返回是否开启了严格授权模式
+        #[ink(message)]
+        pub fn strict_approvals(&self) -> bool {
+            self.strict_approvals
+        }
+
+        // 开启或关闭严格授权模式，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_strict_approvals(&mut self, enabled: bool) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.strict_approvals = enabled;
+            Ok(())
+        }
+
+        // 返回是否只允许授权给白名单中的账号
+        #[ink(message)]
+        pub fn spender_allowlist_enabled(&self) -> bool {
+            self.spender_allowlist_enabled
+        }
+
+        // 开启或关闭授权白名单，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_spender_allowlist_enabled(&mut self, enabled: bool) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.spender_allowlist_enabled = enabled;
+            Ok(())
+        }
+
+        // 返回账号是否在授权白名单中
+        #[ink(message)]
+        pub fn is_spender_allowed(&self, spender: AccountId) -> bool {
+            self.allowed_spenders.contains_key(&spender)
+        }
+
+        // 将账号加入或移出授权白名单，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_spender_allowed(&mut self, spender: AccountId, allowed: bool) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            if allowed {
+                self.allowed_spenders.insert(spender, ());
+            } else {
+                self.allowed_spenders.take(&spender);
+            }
+            Ok(())
+        }
+
+        // 返回单笔转账的最大数量
+        #[ink(message)]
+        pub fn max_transfer_amount(&self) -> Option<Balance> {
+            self.max_transfer_amount
+        }
+
+        // 设置单笔转账的最大数量，None 表示取消限制，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_max_transfer_amount(&mut self, amount: Option<Balance>) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.max_transfer_amount = amount;
+            Ok(())
+        }
+
+        // 设置账号是否不受单笔转账上限的限制，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_transfer_limit_exempt(&mut self, account: AccountId, exempt: bool) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            if exempt {
+                self.transfer_limit_exempt.insert(account, ());
+            } else {
+                self.transfer_limit_exempt.take(&account);
+            }
+            Ok(())
+        }
+
+        // 返回两次转出之间需要间隔的区块数
+        #[ink(message)]
+        pub fn cooldown_blocks(&self) -> BlockNumber {
+            self.cooldown_blocks
+        }
+
+        // 设置两次转出之间需要间隔的区块数，0 表示取消限制，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_cooldown_blocks(&mut self, blocks: BlockNumber) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.cooldown_blocks = blocks;
+            Ok(())
+        }
+
+        // 返回代币的过期时间
+        #[ink(message)]
+        pub fn expires_at(&self) -> Option<Timestamp> {
+            self.expires_at
+        }
+
+        // 设置代币的过期时间，None 表示永不过期，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_expires_at(&mut self, expires_at: Option<Timestamp>) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.expires_at = expires_at;
+            Ok(())
+        }
+
+        // 内部函数，返回代币是否已经过期
+        fn is_expired(&self) -> bool {
+            match self.expires_at {
+                Some(expires_at) => self.env().block_timestamp() >= expires_at,
+                None => false,
+            }
+        }
+
+        // 代币过期后，任何人都可以调用，销毁指定账号剩余的余额，相当于把未使用的额度退回给发行方
+        #[ink(message)]
+        pub fn sweep_expired(&mut self, accounts: Vec<AccountId>) -> Result<()>{
+            if !self.is_expired() {
+                return Err(Error::NotExpired)
+            }
+            for account in accounts {
+                let balance = self.balance_of(account);
+                if balance > 0 {
+                    self.burn_from_account(account, balance)?;
+                }
+            }
+            Ok(())
+        }
+
+        // 返回累计销毁的代币数量
+        #[ink(message)]
+        pub fn total_burned(&self) -> Balance {
+            self.total_burned
+        }
+
+        // 返回流通量，即总供应量减去不计入流通量的账号的余额
+        #[ink(message)]
+        pub fn circulating_supply(&self) -> Balance {
+            let locked: Balance = self.non_circulating
+                .keys()
+                .map(|account| self.balance_of(*account))
+                .sum();
+            self.total_supply.saturating_sub(locked)
+        }
+
+        // 返回账号是否不计入流通量
+        #[ink(message)]
+        pub fn is_non_circulating(&self, account: AccountId) -> bool {
+            self.non_circulating.contains_key(&account)
+        }
+
+        // 设置账号是否不计入流通量，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_non_circulating(&mut self, account: AccountId, non_circulating: bool) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            if non_circulating {
+                self.non_circulating.insert(account, ());
+            } else {
+                self.non_circulating.take(&account);
+            }
+            Ok(())
+        }
+
+        // 返回合规检查合约的地址
+        #[ink(message)]
+        pub fn compliance_registry(&self) -> Option<AccountId> {
+            self.compliance_registry
+        }
+
+        // 设置合规检查合约，None 表示取消检查，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_compliance_registry(&mut self, registry: Option<AccountId>) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.compliance_registry = registry;
+            Ok(())
+        }
+
+        // 取回误转到本合约地址的其他代币，通过跨合约调用该代币的 transfer 转给 to，只能创建者可以调用
+        #[ink(message)]
+        pub fn rescue_tokens(&mut self, token: AccountId, to: AccountId, amount: Balance) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            let result = build_call::<Environment>()
+                .callee(token)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(TRANSFER_SELECTOR))
+                        .push_arg(to)
+                        .push_arg(amount)
+                )
+                .returns::<ReturnType<Result<()>>>()
+                .fire();
+            match result {
+                Ok(Ok(())) => Ok(()),
+                _ => Err(Error::RescueFailed),
+            }
+        }
+
+        // 返回是否为包装原生代币模式
+        #[ink(message)]
+        pub fn wrapped(&self) -> bool {
+            self.wrapped
+        }
+
+        // 包装模式下，按转入的原生代币数量 1:1 给调用者发行代币
+        #[ink(message, payable)]
+        pub fn deposit(&mut self) -> Result<()>{
+            if !self.wrapped {
+                return Err(Error::NotWrapped)
+            }
+            let caller = Self::env().caller();
+            let amount = Self::env().transferred_balance();
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.ensure_cap(amount)?;
+            self.issue_to(caller, amount)?;
+
+            self.env().emit_event( Deposit{
+                account : caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 包装模式下，销毁调用者的代币，并按 1:1 退回原生代币
+        #[ink(message)]
+        pub fn withdraw(&mut self, amount: Balance) -> Result<()>{
+            if !self.wrapped {
+                return Err(Error::NotWrapped)
+            }
+            let caller = Self::env().caller();
+            if self.balance_of(caller) < amount {
+                return Err(Error::InsufficientBalance)
+            }
+            // 先转出原生代币，失败时不会销毁调用者的代币
+            if self.env().transfer(caller, amount).is_err() {
+                return Err(Error::NativeTransferFailed)
+            }
+            self.transfer_from_to(Some(caller), None, amount)?;
+            self.total_supply -= amount;
+
+            self.env().emit_event( Withdrawal{
+                account : caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 任何人都可以转入原生代币作为分红，按持有的份额分给当前所有持有人
+        #[ink(message, payable)]
+        pub fn distribute(&mut self) -> Result<()>{
+            if self.total_shares == 0 {
+                return Err(Error::NoSupply)
+            }
+            let caller = Self::env().caller();
+            let amount = Self::env().transferred_balance();
+            // amount * DIVIDEND_MAGNITUDE 超过几百个原生代币就会超出 u128，用 256 位的中间结果计算
+            let dividend_per_share = mul_div(amount, DIVIDEND_MAGNITUDE, self.total_shares)
+                .and_then(|added| self.dividend_per_share.checked_add(added))
+                .ok_or(Error::DividendOverflow)?;
+            self.dividend_per_share = dividend_per_share;
+
+            self.env().emit_event( DividendsDistributed{
+                from : caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 返回账号可以领取的分红
+        #[ink(message)]
+        pub fn dividends_of(&self, account: AccountId) -> Balance {
+            self.dividends_owed.get(&account).copied().unwrap_or(0) + self.pending_dividends(account)
+        }
+
+        // 领取调用者的全部分红
+        #[ink(message)]
+        pub fn claim_dividends(&mut self) -> Result<()>{
+            let caller = Self::env().caller();
+            self.settle_dividends(caller);
+            let amount = self.dividends_owed.get(&caller).copied().unwrap_or(0);
+            if amount == 0 {
+                return Ok(())
+            }
+            if self.env().transfer(caller, amount).is_err() {
+                return Err(Error::NativeTransferFailed)
+            }
+            self.dividends_owed.take(&caller);
+
+            self.env().emit_event( DividendsClaimed{
+                account : caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 返回每笔转账销毁的比例，单位为万分之一
+        #[ink(message)]
+        pub fn burn_bps(&self) -> u16 {
+            self.burn_bps
+        }
+
+        // 设置每笔转账销毁的比例，单位为万分之一，不能超过 10000，只能创建者可以设置
+        #[ink(message)]
+        pub fn set_burn_bps(&mut self, bps: u16) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            if bps as Balance > BPS_DENOMINATOR {
+                return Err(Error::InvalidBps)
+            }
+            // 包装模式下转账销毁的代币对应的原生代币无人能取回，不能设置销毁比例
+            if self.wrapped && bps > 0 {
+                return Err(Error::WrappedSupply)
+            }
+            self.burn_bps = bps;
+            Ok(())
+        }
+
+        // 销毁代币，任何账号都可以销毁自己持有的代币，销毁后需要减少总供应量
+        #[ink(message)]
+        pub fn burn(&mut self, amount: Balance) -> Result<()>{
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let caller = Self::env().caller();
+            self.burn_from_account(caller, amount)
+        }
+
+        // 内部函数，销毁指定账号的代币，销毁后需要减少总供应量
+        fn burn_from_account(&mut self, from: AccountId, amount: Balance) -> Result<()>{
+            self.transfer_from_to(Some(from), None, amount)?;
+            let total_supply = self.total_supply();
+            self.total_supply = total_supply - amount;
+            self.total_burned += amount;
+            self.env().emit_event( Burned{
+                from,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        // 返回账号是否拥有指定的角色
+        #[ink(message)]
+        pub fn has_role(&self, role: RoleId, account: AccountId) -> bool {
+            self.roles.contains_key(&(role, account))
+        }
+
+        // 授予账号角色，只能创建者可以调用
+        #[ink(message)]
+        pub fn grant_role(&mut self, role: RoleId, account: AccountId) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.roles.insert((role, account), ());
+            Ok(())
+        }
+
+        // 撤销账号的角色，只能创建者可以调用
+        #[ink(message)]
+        pub fn revoke_role(&mut self, role: RoleId, account: AccountId) -> Result<()>{
+            let caller = Self::env().caller();
+            if caller != self.creater {
+                return Err(Error::OnlyForCreater)
+            }
+            self.roles.take(&(role, account));
+            Ok(())
+        }
+
+        // 内部函数，检查调用者是否拥有指定的角色
+        fn ensure_role(&self, role: RoleId) -> Result<()>{
+            let caller = Self::env().caller();
+            if !self.has_role(role, caller) {
+                return Err(Error::MissingRole)
+            }
+            Ok(())
+        }
+
+        // 跨链转入，只能跨链桥角色调用，同一来源链的 nonce 只能使用一次
+        #[ink(message)]
+        pub fn bridge_mint(&mut self, to: AccountId, amount: Balance, src_chain: u32, nonce: u64) -> Result<()>{
+            self.ensure_role(BRIDGE)?;
+            if self.is_renounced() {
+                return Err(Error::IssuanceDisabled)
+            }
+            if self.bridge_in_nonces.contains_key(&(src_chain, nonce)) {
+                return Err(Error::NonceAlreadyUsed)
+            }
+            self.ensure_cap(amount)?;
+            self.mint_to(to, amount)?;
+            self.bridge_in_nonces.insert((src_chain, nonce), ());
+
+            self.env().emit_event( BridgedIn{
+                to,
+                amount,
+                src_chain,
+                nonce,
+            });
+            Ok(())
+        }
+
+        // 返回指定来源链的 nonce 是否已经处理过
+        #[ink(message)]
+        pub fn is_bridge_nonce_used(&self, src_chain: u32, nonce: u64) -> bool {
+            self.bridge_in_nonces.contains_key(&(src_chain, nonce))
+        }
+
+        // 跨链转出，只能跨链桥角色调用，销毁 from 的代币，不是自己的代币时需要 from 授权给跨链桥
+        #[ink(message)]
+        pub fn bridge_burn(&mut self, from: AccountId, amount: Balance, dst_chain: u32) -> Result<()>{
+            self.ensure_role(BRIDGE)?;
+            let caller = Self::env().caller();
+            let allowance = self.allowance(from, caller);
+            if from != caller && allowance < amount {
+                return Err(Error::InsufficientAllowance)
+            }
+            // 先销毁再扣减授权，销毁失败时授权保持不变
+            self.burn_from_account(from, amount)?;
+            if from != caller {
+                self.set_remaining_allowance(from, caller, allowance - amount);
+            }
+
+            let nonce = self.bridge_out_nonce;
+            self.bridge_out_nonce = nonce + 1;
+            self.env().emit_event( BridgedOut{
+                from,
+                amount,
+                dst_chain,
+                nonce,
+            });
+            Ok(())
+        }
+
+        // 强制把 from 的代币转给 to，只能合规角色调用，不需要 from 的授权，也不受转账限制和销毁比例的影响
+        #[ink(message)]
+        pub fn clawback(&mut self, from: AccountId, to: AccountId, amount: Balance) -> Result<()>{
+            self.ensure_role(COMPLIANCE)?;
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            if from == to {
+                return Err(Error::SelfTransfer)
+            }
+            if to == AccountId::from(ZERO_ACCOUNT) {
+                return Err(Error::InvalidRecipient)
+            }
+            self.transfer_from_to(Some(from), Some(to), amount)?;
+
+            let caller = Self::env().caller();
+            self.env().emit_event( Clawback{
+                from,
+                to,
+                amount,
+                operator : caller,
+            });
+            Ok(())
+        }
+    }
+
+    // 计算 a * b / c 并向下取整，乘积用 256 位表示不会溢出，c 为 0 或结果超出 u128 时返回 None
+    fn mul_div(a: u128, b: u128, c: u128) -> Option<u128> {
+        if c == 0 {
+            return None
+        }
+        // 把 a、b 拆成高低 64 位相乘，得到 256 位乘积的高 128 位 hi 和低 128 位 lo
+        let mask = u64::MAX as u128;
+        let (a_hi, a_lo) = (a >> 64, a & mask);
+        let (b_hi, b_lo) = (b >> 64, b & mask);
+        let low = a_lo * b_lo;
+        let cross1 = a_lo * b_hi;
+        let cross2 = a_hi * b_lo;
+        let mid = (low >> 64) + (cross1 & mask) + (cross2 & mask);
+        let lo = (low & mask) | (mid << 64);
+        let hi = a_hi * b_hi + (cross1 >> 64) + (cross2 >> 64) + (mid >> 64);
+        if hi >= c {
+            return None
+        }
+        // 逐位长除法，余数始终小于 c，左移溢出的一位用 carry 记录
+        let mut remainder = hi;
+        let mut quotient: u128 = 0;
+        for bit in (0..128).rev() {
+            let carry = remainder >> 127;
+            remainder = (remainder << 1) | ((lo >> bit) & 1);
+            quotient <<= 1;
+            if carry == 1 || remainder >= c {
+                remainder = remainder.wrapping_sub(c);
+                quotient |= 1;
+            }
+        }
+        Some(quotient)
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
     /// module and test functions are marked with a `#[test]` attribute.
     /// The below code is technically just normal Rust code.
     #[cfg(test)]
@@ -300,93 +2031,461 @@ pub mod erc20 {
             }
         }
 
-        //  测试创建合约
+        //  测试创建合约
+        #[ink::test]
+        fn create_works() {
+            let erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
+            // 检查创建的是各项属性是否设置正确
+            assert_eq!(erc20.name(), String::from("xDOT"));
+            assert_eq!(erc20.symbol(), String::from("DOT"));
+            assert_eq!(erc20.total_supply(), 1_000_000_000);
+            assert_eq!(erc20.balance_of(AccountId::from([0x01; 32])), 1_000_000_000);
+            assert_eq!(
+                erc20.token_info(),
+                TokenInfo {
+                    name: String::from("xDOT"),
+                    symbol: String::from("DOT"),
+                    decimals: 18,
+                    total_supply: 1_000_000_000,
+                    creater: AccountId::from([0x01; 32]),
+                }
+            );
+
+            // 检测触发的时间是不是1个
+            let emitted_events = ink_env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(1, emitted_events.len());
+
+            assert_transfer_event(
+                &emitted_events[0],
+                None,
+                Some(AccountId::from([0x01; 32])),
+                1_000_000_000,
+            );
+        }
+        #[ink::test]
+        fn transfer_works() {
+            // 后边会需要调用修改的接口，所以需要加 mut
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
+            // 返回用于测试的账号(Alice, Bob, Charlie, Django, Eve , Frank)
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            // bob 的余额为0
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            // Alice 转账 10 枚代币给 Bob.
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            // Bob 余额应该是 10.
+            assert_eq!(erc20.balance_of(accounts.bob), 10);
+
+            // 获取事件
+            let emitted_events = ink_env::test::recorded_events().collect::<Vec<_>>();
+            // 应该有两个事件，一个是创建代币转账给 Alice 的，一个是 Alice 转账给 Bob 的
+            assert_eq!( emitted_events.len(), 2);
+
+            // 检测第一个事件创建时候直接转给 Alice 的
+            assert_transfer_event(
+                &emitted_events[0],
+                None,
+                Some(AccountId::from([0x01; 32])),
+                1_000_000_000,
+            );
+            // 检测第二个，是由 Alice 转给 Bob 的
+            assert_transfer_event(
+                &emitted_events[1],
+                Some(AccountId::from([0x01; 32])),
+                Some(AccountId::from([0x02; 32])),
+                10,
+            );
+        }
+
+        #[ink::test]
+        fn transfer_from_works(){
+            // 后边会需要调用修改的接口，所以需要加 mut
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
+            // 返回用于测试的账号(Alice, Bob, Charlie, Django, Eve , Frank)
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            // 从 alice 往 eve 账号转账会报错，错误是没有授权
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.eve, 10),
+                Err(Error::InsufficientAllowance)
+            );
+            // 授权 Bob 可以代表自己转账 10 个代币
+            assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
+
+            // 应该有两个事件发生，部署时候的初始转账，授权的事件
+            assert_eq!(ink_env::test::recorded_events().count(), 2);
+
+            // 获得当前合约部署的地址
+            let callee = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .unwrap_or([0x0; 32].into());
+            // Create call.
+            // 创建 call 
+            let mut data =
+                ink_env::test::CallData::new(ink_env::call::Selector::new([0x00; 4]));
+            data.push_arg(&accounts.bob);
+            // 将 Bob 设置为调用者
+            ink_env::test::push_execution_context::<ink_env::DefaultEnvironment>(
+                accounts.bob,
+                callee,
+                1000000,
+                1000000,
+                data,
+            );
+
+            // Bob 调用，从 Alice 账号转 10 个代币给 Eve
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.eve, 10),
+                Ok(())
+            );
+            // 确认 Eve 的代币数量
+            assert_eq!(erc20.balance_of(accounts.eve), 10);
+
+            // 检查所有的转账事件（第一个和第三个，第二个是授权的，跳过）
+            let emitted_events = ink_env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 3);
+            assert_transfer_event(
+                &emitted_events[0],
+                None,
+                Some(AccountId::from([0x01; 32])),
+                1_000_000_000,
+            );
+            assert_transfer_event(
+                &emitted_events[2],
+                Some(AccountId::from([0x01; 32])),
+                Some(AccountId::from([0x05; 32])),
+                10,
+            );
+
+        }
+
+        #[ink::test]
+        fn issue_and_burn_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            // 增发需要先排队，等待期内不能执行
+            assert_eq!(erc20.queue_issue(100), Ok(0));
+            assert_eq!(erc20.execute_issue(0), Err(Error::TimelockNotExpired));
+            for _ in 0..erc20.issue_delay() {
+                ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot advance block");
+            }
+            assert_eq!(erc20.execute_issue(0), Ok(()));
+            assert_eq!(erc20.execute_issue(0), Err(Error::UnknownIssue));
+            assert_eq!(erc20.burn(30), Ok(()));
+            assert_eq!(erc20.total_supply(), 1_000_000_070);
+            assert_eq!(erc20.balance_of(accounts.alice), 1_000_000_070);
+
+            // 部署转账，排队事件，增发的转账、Minted 和执行事件，销毁的转账和 Burned 事件
+            let emitted_events = ink_env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 7);
+            assert_transfer_event(&emitted_events[2], None, Some(accounts.alice), 100);
+            assert_transfer_event(&emitted_events[5], Some(accounts.alice), None, 30);
+
+            let decoded_event = <Event as scale::Decode>::decode(&mut &emitted_events[3].data[..])
+                .expect("encountered invalid contract event data buffer");
+            if let Event::Minted(Minted { to, amount }) = decoded_event {
+                assert_eq!(to, accounts.alice);
+                assert_eq!(amount, 100);
+            } else {
+                panic!("encountered unexpected event kind: expected a Minted event")
+            }
+            let decoded_event = <Event as scale::Decode>::decode(&mut &emitted_events[6].data[..])
+                .expect("encountered invalid contract event data buffer");
+            if let Event::Burned(Burned { from, amount }) = decoded_event {
+                assert_eq!(from, accounts.alice);
+                assert_eq!(amount, 30);
+            } else {
+                panic!("encountered unexpected event kind: expected a Burned event")
+            }
+
+            // 取消后的增发不能再执行，等待区块数不能缩短
+            assert_eq!(erc20.queue_issue(100), Ok(1));
+            assert_eq!(erc20.cancel_issue(1), Ok(()));
+            assert_eq!(erc20.execute_issue(1), Err(Error::UnknownIssue));
+            assert_eq!(erc20.set_issue_delay(0), Err(Error::DelayTooShort));
+
+            // 创建者放弃权限后不能再增发，之前授予的跨链桥角色和设置的空投也不能
+            assert_eq!(erc20.queue_issue(100), Ok(2));
+            assert_eq!(erc20.grant_role(BRIDGE, accounts.alice), Ok(()));
+            assert_eq!(erc20.set_claim_root(Hash::from([0x01; 32]), 100), Ok(()));
+            assert_eq!(erc20.renounce_creater(), Ok(()));
+            assert!(erc20.is_renounced());
+            assert_eq!(erc20.queue_issue(100), Err(Error::OnlyForCreater));
+            assert_eq!(erc20.execute_issue(2), Err(Error::OnlyForCreater));
+            assert_eq!(erc20.bridge_mint(accounts.bob, 10, 1, 0), Err(Error::IssuanceDisabled));
+            assert_eq!(erc20.claim(100, Vec::new()), Err(Error::IssuanceDisabled));
+        }
+
+        #[ink::test]
+        fn holders_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            assert_eq!(erc20.holders(0, 10), vec![(accounts.alice, 1_000_000_000)]);
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            assert_eq!(erc20.transfer(accounts.charlie, 20), Ok(()));
+            assert_eq!(erc20.holders(1, 10), vec![(accounts.bob, 10), (accounts.charlie, 20)]);
+            assert_eq!(erc20.holders(0, 1), vec![(accounts.alice, 999_999_970)]);
+
+            // Alice 把余额全部销毁后，最后一个持有人 Charlie 移到她的位置
+            assert_eq!(erc20.burn(999_999_970), Ok(()));
+            assert_eq!(erc20.holders(0, 10), vec![(accounts.charlie, 20), (accounts.bob, 10)]);
+
+            assert_eq!(erc20.holders_count(), 2);
+            assert_eq!(erc20.holders_added(), 3);
+            assert_eq!(erc20.holders_removed(), 1);
+        }
+
+        #[ink::test]
+        fn wrapped_deposit_and_withdraw_works(){
+            let mut erc20 = Erc20::new_wrapped(String::from("Wrapped DOT"), String::from("WDOT"));
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+            assert_eq!(erc20.total_supply(), 0);
+
+            // Alice 存入 100 个原生代币
+            let callee = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .unwrap_or([0x0; 32].into());
+            let data =
+                ink_env::test::CallData::new(ink_env::call::Selector::new([0x00; 4]));
+            ink_env::test::push_execution_context::<ink_env::DefaultEnvironment>(
+                accounts.alice,
+                callee,
+                1000000,
+                100,
+                data,
+            );
+            assert_eq!(erc20.deposit(), Ok(()));
+            ink_env::test::pop_execution_context();
+            assert_eq!(erc20.balance_of(accounts.alice), 100);
+            assert_eq!(erc20.total_supply(), 100);
+            // 链下环境不会把转入的原生代币记到合约账户上，需要手动设置合约余额
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(callee, 100)
+                .expect("Cannot set account balance");
+
+            // 取回 40 个原生代币
+            assert_eq!(erc20.withdraw(40), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 60);
+            assert_eq!(erc20.total_supply(), 60);
+            assert_eq!(erc20.withdraw(100), Err(Error::InsufficientBalance));
+
+            // 包装模式下不能通过其他方式增发，也不能改变供应量和原生代币的对应关系
+            assert_eq!(erc20.airdrop(vec![(accounts.bob, 10)]), Err(Error::WrappedSupply));
+            assert_eq!(erc20.queue_issue(10), Err(Error::WrappedSupply));
+            assert_eq!(erc20.rebase(10), Err(Error::WrappedSupply));
+            assert_eq!(erc20.set_burn_bps(100), Err(Error::WrappedSupply));
+            assert_eq!(erc20.total_supply(), 60);
+        }
+
         #[ink::test]
-        fn create_works() {
-            let erc20 = Erc20::new(b"xDOT".to_vec(), b"DOT".to_vec(), 1_000_000_000);
-            // 检查创建的是各项属性是否设置正确
-            assert_eq!(erc20.name(), b"xDOT".to_vec());
-            assert_eq!(erc20.symbol(), b"DOT".to_vec());
-            assert_eq!(erc20.total_supply(), 1_000_000_000);
-            assert_eq!(erc20.balance_of(AccountId::from([0x01; 32])), 1_000_000_000);
+        fn airdrop_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
 
-            // 检测触发的时间是不是1个
-            let emitted_events = ink_env::test::recorded_events().collect::<Vec<_>>();
-            assert_eq!(1, emitted_events.len());
+            assert_eq!(erc20.airdrop(vec![(accounts.bob, 10), (accounts.charlie, 20)]), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 10);
+            assert_eq!(erc20.balance_of(accounts.charlie), 20);
+            assert_eq!(erc20.total_supply(), 1_000_000_030);
 
-            assert_transfer_event(
-                &emitted_events[0],
-                None,
-                Some(AccountId::from([0x01; 32])),
-                1_000_000_000,
+            // 每 10 个区块最多增发 50
+            assert_eq!(erc20.set_mint_rate_limit(Some(50), 10), Ok(()));
+            assert_eq!(erc20.airdrop(vec![(accounts.bob, 30)]), Ok(()));
+            assert_eq!(erc20.airdrop(vec![(accounts.bob, 30)]), Err(Error::MintRateExceeded));
+            assert_eq!(erc20.minted_in_current_period(), 30);
+            for _ in 0..10 {
+                ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot advance block");
+            }
+            assert_eq!(erc20.airdrop(vec![(accounts.bob, 30)]), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 70);
+
+            // 超过单次空投的账号数量上限
+            assert_eq!(erc20.set_max_airdrop_recipients(1), Ok(()));
+            assert_eq!(
+                erc20.airdrop(vec![(accounts.bob, 10), (accounts.charlie, 20)]),
+                Err(Error::TooManyRecipients)
             );
         }
+
         #[ink::test]
-        fn transfer_works() {
-            // 后边会需要调用修改的接口，所以需要加 mut
-            let mut erc20 = Erc20::new(b"xDOT".to_vec(), b"DOT".to_vec(), 1_000_000_000);
-            // 返回用于测试的账号(Alice, Bob, Charlie, Django, Eve , Frank)
+        fn claim_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
             let accounts =
                 ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
                     .expect("Cannot get accounts");
 
-            // bob 的余额为0
-            assert_eq!(erc20.balance_of(accounts.bob), 0);
-            // Alice 转账 10 枚代币给 Bob.
-            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
-            // Bob 余额应该是 10.
+            // 两个叶子的 merkle 树：(Alice, 100) 和 (Bob, 200)
+            let leaf = |account: AccountId, amount: Balance| {
+                let mut output = <Blake2x256 as HashOutput>::Type::default();
+                ink_env::hash_encoded::<Blake2x256, _>(&(account, amount), &mut output);
+                Hash::from(output)
+            };
+            let alice_leaf = leaf(accounts.alice, 100);
+            let bob_leaf = leaf(accounts.bob, 200);
+            let root = Erc20::merkle_root(alice_leaf, &[bob_leaf]);
+
+            assert_eq!(erc20.claim(100, vec![bob_leaf]), Err(Error::ClaimNotStarted));
+            assert_eq!(erc20.set_claim_root(root, 300), Ok(()));
+            // 数量不对证明无法通过
+            assert_eq!(erc20.claim(200, vec![bob_leaf]), Err(Error::InvalidProof));
+            assert_eq!(erc20.claim(100, vec![bob_leaf]), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 1_000_000_100);
+            assert_eq!(erc20.claim_progress(), (300, 100));
+            // 同一个叶子不能重复领取
+            assert_eq!(erc20.claim(100, vec![bob_leaf]), Err(Error::AlreadyClaimed));
+        }
+
+        #[ink::test]
+        fn bridge_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            // 没有跨链桥角色不能调用
+            assert_eq!(erc20.bridge_mint(accounts.bob, 10, 1, 0), Err(Error::MissingRole));
+            assert_eq!(erc20.grant_role(BRIDGE, accounts.alice), Ok(()));
+            assert!(erc20.has_role(BRIDGE, accounts.alice));
+
+            assert_eq!(erc20.bridge_mint(accounts.bob, 10, 1, 0), Ok(()));
             assert_eq!(erc20.balance_of(accounts.bob), 10);
+            // 同一个 nonce 不能重放
+            assert_eq!(erc20.bridge_mint(accounts.bob, 10, 1, 0), Err(Error::NonceAlreadyUsed));
+            assert!(erc20.is_bridge_nonce_used(1, 0));
 
-            // 获取事件
-            let emitted_events = ink_env::test::recorded_events().collect::<Vec<_>>();
-            // 应该有两个事件，一个是创建代币转账给 Alice 的，一个是 Alice 转账给 Bob 的
-            assert_eq!( emitted_events.len(), 2);
+            // 跨链转入同样受增发额度限制，超出额度时 nonce 不会被占用
+            assert_eq!(erc20.set_mint_rate_limit(Some(50), 10), Ok(()));
+            assert_eq!(erc20.bridge_mint(accounts.bob, 60, 1, 1), Err(Error::MintRateExceeded));
+            assert!(!erc20.is_bridge_nonce_used(1, 1));
+            assert_eq!(erc20.bridge_mint(accounts.bob, 50, 1, 1), Ok(()));
+            assert_eq!(erc20.minted_in_current_period(), 50);
+            assert_eq!(erc20.set_mint_rate_limit(None, 0), Ok(()));
 
-            // 检测第一个事件创建时候直接转给 Alice 的
-            assert_transfer_event(
-                &emitted_events[0],
-                None,
-                Some(AccountId::from([0x01; 32])),
-                1_000_000_000,
-            );
-            // 检测第二个，是由 Alice 转给 Bob 的
-            assert_transfer_event(
-                &emitted_events[1],
-                Some(AccountId::from([0x01; 32])),
-                Some(AccountId::from([0x02; 32])),
-                10,
+            // 没有授权不能销毁 Bob 的代币
+            assert_eq!(erc20.bridge_burn(accounts.bob, 10, 2), Err(Error::InsufficientAllowance));
+            assert_eq!(erc20.bridge_burn(accounts.alice, 100, 2), Ok(()));
+            assert_eq!(erc20.total_supply(), 1_000_000_000 + 60 - 100);
+        }
+
+        #[ink::test]
+        fn clawback_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+
+            // 没有合规角色不能调用
+            assert_eq!(erc20.clawback(accounts.bob, accounts.charlie, 40), Err(Error::MissingRole));
+            assert_eq!(erc20.grant_role(COMPLIANCE, accounts.alice), Ok(()));
+
+            // 不需要 Bob 的授权
+            assert_eq!(erc20.clawback(accounts.bob, accounts.charlie, 40), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 60);
+            assert_eq!(erc20.balance_of(accounts.charlie), 40);
+            assert_eq!(
+                erc20.clawback(accounts.bob, accounts.charlie, 100),
+                Err(Error::InsufficientBalance)
             );
         }
 
         #[ink::test]
-        fn transfer_from_works(){
-            // 后边会需要调用修改的接口，所以需要加 mut
-            let mut erc20 = Erc20::new(b"xDOT".to_vec(), b"DOT".to_vec(), 1_000_000_000);
-            // 返回用于测试的账号(Alice, Bob, Charlie, Django, Eve , Frank)
+        fn circulating_supply_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+            assert_eq!(erc20.transfer(accounts.bob, 300), Ok(()));
+            assert_eq!(erc20.burn(100), Ok(()));
+            assert_eq!(erc20.total_burned(), 100);
+            assert_eq!(erc20.circulating_supply(), 900);
+
+            // Bob 的余额不计入流通量
+            assert_eq!(erc20.set_non_circulating(accounts.bob, true), Ok(()));
+            assert!(erc20.is_non_circulating(accounts.bob));
+            assert_eq!(erc20.circulating_supply(), 600);
+            assert_eq!(erc20.set_non_circulating(accounts.bob, false), Ok(()));
+            assert_eq!(erc20.circulating_supply(), 900);
+        }
+
+        #[ink::test]
+        fn non_transferable_works(){
+            let mut erc20 = Erc20::new_with_transferable(String::from("Points"), String::from("PTS"), 1_000, false);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+            assert!(!erc20.transferable());
+
+            assert_eq!(erc20.transfer(accounts.bob, 10), Err(Error::NonTransferable));
+
+            // 增发和销毁不受影响
+            assert_eq!(erc20.airdrop(vec![(accounts.bob, 20)]), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 20);
+            assert_eq!(erc20.burn(100), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 900);
+        }
+
+        #[ink::test]
+        fn expiry_works(){
+            let mut erc20 = Erc20::new(String::from("Voucher"), String::from("VCH"), 1_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.sweep_expired(vec![accounts.bob]), Err(Error::NotExpired));
+
+            let now = ink_env::block_timestamp::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get block timestamp");
+            assert_eq!(erc20.set_expires_at(Some(now + 1)), Ok(()));
+            assert_eq!(erc20.expires_at(), Some(now + 1));
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+
+            // 过期后不能转账，剩余的余额可以被销毁
+            assert_eq!(erc20.transfer(accounts.bob, 10), Err(Error::TokenExpired));
+            assert_eq!(erc20.sweep_expired(vec![accounts.bob, accounts.charlie]), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            assert_eq!(erc20.total_supply(), 900);
+        }
+
+        #[ink::test]
+        fn multisig_issue_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
             let accounts =
                 ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
                     .expect("Cannot get accounts");
 
-            // 从 alice 往 eve 账号转账会报错，错误是没有授权
             assert_eq!(
-                erc20.transfer_from(accounts.alice, accounts.eve, 10),
-                Err(Error::InsufficientAllowance)
+                erc20.set_signers(vec![accounts.alice, accounts.bob], 3),
+                Err(Error::InvalidThreshold)
             );
-            // 授权 Bob 可以代表自己转账 10 个代币
-            assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
+            assert_eq!(erc20.set_signers(vec![accounts.alice, accounts.bob], 2), Ok(()));
+            assert_eq!(erc20.set_signers(vec![accounts.alice], 1), Err(Error::SignersAlreadySet));
+            // 设置多签后创建者不能再单独增发
+            assert_eq!(erc20.queue_issue(100), Err(Error::MultisigRequired));
 
-            // 应该有两个事件发生，部署时候的初始转账，授权的事件
-            assert_eq!(ink_env::test::recorded_events().count(), 2);
+            // Alice 发起提案自动确认，还差 Bob 的确认
+            assert_eq!(erc20.propose_issue(100), Ok(0));
+            assert_eq!(erc20.confirm(0), Err(Error::AlreadyConfirmed));
+            assert_eq!(erc20.execute(0), Err(Error::NotEnoughConfirmations));
 
-            // 获得当前合约部署的地址
+            // 将 Bob 设置为调用者进行确认
             let callee = ink_env::account_id::<ink_env::DefaultEnvironment>()
                 .unwrap_or([0x0; 32].into());
-            // Create call.
-            // 创建 call 
-            let mut data =
+            let data =
                 ink_env::test::CallData::new(ink_env::call::Selector::new([0x00; 4]));
-            data.push_arg(&accounts.bob);
-            // 将 Bob 设置为调用者
             ink_env::test::push_execution_context::<ink_env::DefaultEnvironment>(
                 accounts.bob,
                 callee,
@@ -394,33 +2493,301 @@ pub mod erc20 {
                 1000000,
                 data,
             );
+            assert_eq!(erc20.confirm(0), Ok(()));
+            assert_eq!(erc20.execute(0), Ok(()));
+            assert_eq!(erc20.execute(0), Err(Error::ProposalAlreadyExecuted));
+            ink_env::test::pop_execution_context();
 
-            // Bob 调用，从 Alice 账号转 10 个代币给 Eve
-            assert_eq!(
-                erc20.transfer_from(accounts.alice, accounts.eve, 10),
-                Ok(())
+            assert_eq!(erc20.balance_of(accounts.alice), 1_000_000_100);
+            let proposals = erc20.issue_proposals(0, 10);
+            assert_eq!(proposals.len(), 1);
+            assert_eq!(proposals[0].1.confirmations, 2);
+            assert!(proposals[0].1.executed);
+        }
+
+        #[ink::test]
+        fn dividends_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+            let callee = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .unwrap_or([0x0; 32].into());
+            let distribute = |erc20: &mut Erc20, amount: Balance| {
+                let data =
+                    ink_env::test::CallData::new(ink_env::call::Selector::new([0x00; 4]));
+                ink_env::test::push_execution_context::<ink_env::DefaultEnvironment>(
+                    accounts.alice,
+                    callee,
+                    1000000,
+                    amount,
+                    data,
+                );
+                assert_eq!(erc20.distribute(), Ok(()));
+                ink_env::test::pop_execution_context();
+            };
+
+            // Alice 持有全部代币时分红 100，之后转一半给 Bob 再分红 100
+            distribute(&mut erc20, 100);
+            assert_eq!(erc20.transfer(accounts.bob, 500), Ok(()));
+            distribute(&mut erc20, 100);
+            assert_eq!(erc20.dividends_of(accounts.alice), 150);
+            assert_eq!(erc20.dividends_of(accounts.bob), 50);
+
+            // 链下环境不会把转入的原生代币记到合约账户上，需要手动设置合约余额
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(callee, 200)
+                .expect("Cannot set account balance");
+            assert_eq!(erc20.claim_dividends(), Ok(()));
+            assert_eq!(erc20.dividends_of(accounts.alice), 0);
+            assert_eq!(erc20.dividends_of(accounts.bob), 50);
+
+            // rebase 只改变余额，不改变已经分配的分红
+            assert_eq!(erc20.rebase(1_000), Ok(()));
+            assert_eq!(erc20.dividends_of(accounts.bob), 50);
+            distribute(&mut erc20, 100);
+            assert_eq!(erc20.dividends_of(accounts.alice), 50);
+            assert_eq!(erc20.dividends_of(accounts.bob), 100);
+        }
+
+        #[ink::test]
+        fn large_dividends_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000_000_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+            let callee = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .unwrap_or([0x0; 32].into());
+            assert_eq!(erc20.transfer(accounts.bob, 250_000_000_000_000), Ok(()));
+
+            // 分红数量乘以放大倍数超出 u128 时仍然按份额准确分配
+            let amount: Balance = 1_000_000_000_000_000_000_000;
+            let data =
+                ink_env::test::CallData::new(ink_env::call::Selector::new([0x00; 4]));
+            ink_env::test::push_execution_context::<ink_env::DefaultEnvironment>(
+                accounts.alice,
+                callee,
+                1000000,
+                amount,
+                data,
             );
-            // 确认 Eve 的代币数量
-            assert_eq!(erc20.balance_of(accounts.eve), 10);
+            assert_eq!(erc20.distribute(), Ok(()));
+            ink_env::test::pop_execution_context();
+            assert_eq!(erc20.dividends_of(accounts.alice), amount / 4 * 3);
+            assert_eq!(erc20.dividends_of(accounts.bob), amount / 4);
 
-            // 检查所有的转账事件（第一个和第三个，第二个是授权的，跳过）
-            let emitted_events = ink_env::test::recorded_events().collect::<Vec<_>>();
-            assert_eq!(emitted_events.len(), 3);
-            assert_transfer_event(
-                &emitted_events[0],
-                None,
-                Some(AccountId::from([0x01; 32])),
-                1_000_000_000,
+            assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+            assert_eq!(mul_div(u128::MAX, 2, 3), Some(u128::MAX / 3 * 2));
+            assert_eq!(mul_div(u128::MAX, 2, 1), None);
+            assert_eq!(mul_div(1, 1, 0), None);
+        }
+
+        #[ink::test]
+        fn burn_on_transfer_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            assert_eq!(erc20.set_burn_bps(10_001), Err(Error::InvalidBps));
+            // 每笔转账销毁 1%
+            assert_eq!(erc20.set_burn_bps(100), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 1_000), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 990);
+            assert_eq!(erc20.balance_of(accounts.alice), 999_000);
+            assert_eq!(erc20.total_supply(), 999_990);
+            assert_eq!(erc20.transfer(accounts.bob, 1_000_000), Err(Error::InsufficientBalance));
+        }
+
+        #[ink::test]
+        fn error_codes_work(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            assert_eq!(erc20.transfer(accounts.bob, 0), Err(Error::ZeroAmount));
+            assert_eq!(erc20.transfer(accounts.alice, 10), Err(Error::SelfTransfer));
+            assert_eq!(
+                erc20.transfer(AccountId::from([0x00; 32]), 10),
+                Err(Error::InvalidRecipient)
             );
-            assert_transfer_event(
-                &emitted_events[2],
-                Some(AccountId::from([0x01; 32])),
-                Some(AccountId::from([0x05; 32])),
-                10,
+            assert_eq!(erc20.set_cap(Some(999)), Err(Error::CapExceeded));
+            assert_eq!(erc20.set_cap(Some(1_010)), Ok(()));
+            assert_eq!(erc20.airdrop(vec![(accounts.bob, 20)]), Err(Error::CapExceeded));
+
+            // 错误编号是固定的 SCALE 编码值
+            assert_eq!(scale::Encode::encode(&Error::InsufficientBalance), vec![0]);
+            assert_eq!(scale::Encode::encode(&Error::CapExceeded), vec![36]);
+        }
+
+        #[ink::test]
+        fn operator_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            assert_eq!(erc20.set_operator(accounts.bob, true), Ok(()));
+            assert!(erc20.is_operator(accounts.alice, accounts.bob));
+
+            // 将 Bob 设置为调用者，作为操作员转走 Alice 的代币
+            let callee = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .unwrap_or([0x0; 32].into());
+            let data =
+                ink_env::test::CallData::new(ink_env::call::Selector::new([0x00; 4]));
+            ink_env::test::push_execution_context::<ink_env::DefaultEnvironment>(
+                accounts.bob,
+                callee,
+                1000000,
+                1000000,
+                data,
             );
+            assert_eq!(erc20.transfer_from(accounts.alice, accounts.eve, 600), Ok(()));
+            ink_env::test::pop_execution_context();
+            assert_eq!(erc20.balance_of(accounts.eve), 600);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+
+            assert_eq!(erc20.set_operator(accounts.bob, false), Ok(()));
+            assert!(!erc20.is_operator(accounts.alice, accounts.bob));
+        }
 
+        #[ink::test]
+        fn approve_with_expiry_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            // 授权 Bob 10 个代币，在下一个区块的时间过期
+            let now = ink_env::block_timestamp::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get block timestamp");
+            assert_eq!(erc20.approve_with_expiry(accounts.bob, 10, now + 1), Ok(()));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 10);
+
+            // 出块后授权过期，额度视为 0
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+
+            // 普通授权不会过期
+            assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 10);
+        }
+
+        #[ink::test]
+        fn strict_approvals_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            assert_eq!(erc20.set_strict_approvals(true), Ok(()));
+            assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
+            // 额度不为 0 时不能直接修改为其他非 0 的值
+            assert_eq!(erc20.approve(accounts.bob, 20), Err(Error::NonZeroAllowance));
+            // 先清零，再重新授权
+            assert_eq!(erc20.approve(accounts.bob, 0), Ok(()));
+            assert_eq!(erc20.approve(accounts.bob, 20), Ok(()));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 20);
+        }
+
+        #[ink::test]
+        fn spender_allowlist_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            assert_eq!(erc20.approve(accounts.charlie, 10), Ok(()));
+            assert_eq!(erc20.set_spender_allowlist_enabled(true), Ok(()));
+            assert_eq!(erc20.set_spender_allowed(accounts.bob, true), Ok(()));
+            assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
+            assert_eq!(erc20.approve(accounts.charlie, 20), Err(Error::SpenderNotAllowed));
+            // 撤销授权不受白名单限制
+            assert_eq!(erc20.approve(accounts.charlie, 0), Ok(()));
+        }
+
+        #[ink::test]
+        fn cooldown_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000_000_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            assert_eq!(erc20.set_cooldown_blocks(2), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            // 冷却期内再次转出会报错
+            assert_eq!(erc20.transfer(accounts.bob, 10), Err(Error::CooldownActive));
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+            assert_eq!(erc20.transfer(accounts.bob, 10), Err(Error::CooldownActive));
+            // 冷却期结束后可以继续转出
+            ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                .expect("Cannot advance block");
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 20);
+        }
+
+        #[ink::test]
+        fn rebase_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+
+            assert_eq!(erc20.transfer(accounts.bob, 250), Ok(()));
+            let bob_shares = erc20.shares_of(accounts.bob);
+
+            // 总供应量翻倍，所有余额按比例翻倍，份额不变
+            assert_eq!(erc20.rebase(1_000), Ok(()));
+            assert_eq!(erc20.total_supply(), 2_000);
+            assert_eq!(erc20.balance_of(accounts.alice), 1_500);
+            assert_eq!(erc20.balance_of(accounts.bob), 500);
+            assert_eq!(erc20.shares_of(accounts.bob), bob_shares);
+
+            // rebase 之后转账按新的余额计算
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 600);
+
+            // 收缩到原来的四分之一
+            assert_eq!(erc20.rebase(-1_500), Ok(()));
+            assert_eq!(erc20.total_supply(), 500);
+            assert_eq!(erc20.balance_of(accounts.alice), 350);
+            assert_eq!(erc20.balance_of(accounts.bob), 150);
+
+            assert_eq!(erc20.rebase(-500), Err(Error::InvalidRebase));
         }
 
+        #[ink::test]
+        fn compliance_registry_works(){
+            let mut erc20 = Erc20::new(String::from("xDOT"), String::from("DOT"), 1_000);
+            let accounts =
+                ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot get accounts");
+            assert_eq!(erc20.compliance_registry(), None);
+
+            let callee = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .unwrap_or([0x0; 32].into());
+            let mut data =
+                ink_env::test::CallData::new(ink_env::call::Selector::new([0x00; 4]));
+            data.push_arg(&accounts.bob);
+            ink_env::test::push_execution_context::<ink_env::DefaultEnvironment>(
+                accounts.bob,
+                callee,
+                1000000,
+                1000000,
+                data,
+            );
+            assert_eq!(erc20.set_compliance_registry(Some(accounts.django)), Err(Error::OnlyForCreater));
+            ink_env::test::pop_execution_context();
+
+            assert_eq!(erc20.set_compliance_registry(Some(accounts.django)), Ok(()));
+            assert_eq!(erc20.compliance_registry(), Some(accounts.django));
+            assert_eq!(erc20.set_compliance_registry(None), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+        }
 
     }
     /// For calculating the event topic hash.
@@ -1,15 +1,76 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use ink_env::Environment;
 use ink_lang as ink;
 
-use ink_prelude::format;
+// 通过链扩展从运行时的价格预言机读取质押币种的价格，与 randkey 的 fetch_price 是同一个函数（func_id 1120），参数和返回值一致
+#[ink::chain_extension]
+pub trait FetchPrice {
+    type ErrorCode = PriceReadErr;
 
-#[ink::contract]
+    // 返回运行时中编号为 asset 的币种价格（1 个质押代币值多少基础代币，放大了 PRICE_PRECISION 倍）以及价格的更新时间
+    #[ink(extension = 1120, returns_result = false)]
+    fn fetch_price(asset: u32) -> (u128, <ink_env::DefaultEnvironment as Environment>::Timestamp);
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum PriceReadErr {
+    FailGetPrice,
+}
+
+impl ink_env::chain_extension::FromStatusCode for PriceReadErr {
+    fn from_status_code(status_code: u32) -> Result<(), Self> {
+        match status_code {
+            0 => Ok(()),
+            // 与 randkey 的 RandomReadErr 使用同一套状态码，9 表示预言机没有该币种的价格
+            9 => Err(Self::FailGetPrice),
+            _ => panic!("encountered unknown status code"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum CustomEnvironment {}
+
+impl Environment for CustomEnvironment {
+    const MAX_EVENT_TOPICS: usize =
+        <ink_env::DefaultEnvironment as Environment>::MAX_EVENT_TOPICS;
+
+    type AccountId = <ink_env::DefaultEnvironment as Environment>::AccountId;
+    type Balance = <ink_env::DefaultEnvironment as Environment>::Balance;
+    type Hash = <ink_env::DefaultEnvironment as Environment>::Hash;
+    type BlockNumber = <ink_env::DefaultEnvironment as Environment>::BlockNumber;
+    type Timestamp = <ink_env::DefaultEnvironment as Environment>::Timestamp;
+
+    type ChainExtension = FetchPrice;
+}
+
+// 价格预言机合约需要实现的接口，设置了预言机合约后用它代替链扩展读取价格，不同的部署可以使用不同的价格来源
+#[ink::trait_definition]
+pub trait PriceOracle {
+    // 返回 1 个代币值多少基础代币（放大了 PRICE_PRECISION 倍）以及价格的更新时间
+    #[ink(message)]
+    fn price(&self, token: ink_env::AccountId) -> (u128, u64);
+}
+
+// 闪电贷的接收合约需要实现的接口，借出的基础代币转入后被调用，返回前需要授权本合约转回借款数量加手续费
+#[ink::trait_definition]
+pub trait FlashLoanReceiver {
+    // initiator 为发起闪电贷的用户，data 为发起时传入的任意数据，返回 false 表示拒绝这笔闪电贷
+    #[ink(message)]
+    fn on_flash_loan(&mut self, initiator: ink_env::AccountId, token: ink_env::AccountId, amount: u128, fee: u128, data: ink_prelude::vec::Vec<u8>) -> bool;
+}
+
+#[ink::contract(env = crate::CustomEnvironment)]
 mod loan {
     use ink_storage::collections::HashMap as StorageHashMap;
-    use erc20::Erc20;
-    use ink_env::call::FromAccountId;
-    use crate::format;
+    use ink_storage::collections::Vec as StorageVec;
+    use ink_env::call::{build_call, utils::ReturnType, ExecutionInput, Selector};
+    use ink_env::ReturnFlags;
+    use ink_prelude::vec::Vec;
+    use ink_storage::traits::{PackedLayout, SpreadLayout};
 
     #[ink(storage)]
     pub struct Loan {
@@ -21,13 +82,769 @@ mod loan {
         borrowings_balance : Balance,
         // 总共借出的数量
         total_borrowings : Balance,
-        // 最大借款比例 质押币种 -> 借款比例
-        min_collateral_ratio: StorageHashMap< AccountId, u32>,
+        // 支持的质押币种及其风险参数 质押币种 -> 风险参数
+        collateral_configs: StorageHashMap<AccountId, CollateralConfig>,
         // 质押代币数据，(用户, 质押币种) -> 质押数量
         pledges : StorageHashMap<(AccountId, AccountId), Balance>,
         // 借款数量：借款用户 -> 借款数量
         borrowings : StorageHashMap<AccountId, Balance>,
+        // 价格预言机合约，None 表示通过链扩展读取价格
+        price_oracle: Option<AccountId>,
+        // 全局借款指数，从 INDEX_PRECISION 开始按区块累积利息
+        borrow_index: u128,
+        // 上次累积利息的区块高度
+        last_accrual_block: BlockNumber,
+        // 用户上次结算借款时的借款指数，借款人 -> 借款指数
+        account_borrow_index: StorageHashMap<AccountId, u128>,
+        // 存款人持有的资金池份额，存款人 -> 份额，份额对应的基础代币按资金池总价值等比例增长，存款人以此获得利息
+        supply_shares: StorageHashMap<AccountId, Balance>,
+        // 所有存款人份额的总和
+        total_supply_shares: Balance,
+        // 份额可以像 ERC20 一样转让和授权，(份额持有人, 被授权人) -> 授权数量
+        share_allowances: StorageHashMap<(AccountId, AccountId), Balance>,
+        // 各个质押币种的质押总量
+        total_pledged: StorageHashMap<AccountId, Balance>,
+        // 质押币种的质押总量上限，没有记录表示不限制，基础代币的上限在 config 中
+        supply_caps: StorageHashMap<AccountId, Balance>,
+        // 协议累积的储备金，不属于存款人
+        protocol_reserves: Balance,
+        // 守护者账号，可以和管理者一样暂停功能，但不能修改经济参数
+        guardian: Option<AccountId>,
+        // 各个功能的暂停状态
+        pause_state: PauseState,
+        // 固定期限借款，每个用户同时只能有一笔，借款人 -> 借款
+        fixed_loans: StorageHashMap<AccountId, FixedLoan>,
+        // 固定期限借款尚未归还的本金总和
+        total_fixed_principal: Balance,
+        // 信用委托额度，(委托人, 被委托人) -> 被委托人还可以用委托人的质押借出的数量
+        credit_allowances: StorageHashMap<(AccountId, AccountId), Balance>,
+        // 被委托人借出且尚未归还的本金，(委托人, 被委托人) -> 本金，债务本身记在委托人名下
+        delegated_debts: StorageHashMap<(AccountId, AccountId), Balance>,
+        // 不健康仓位开始计时的区块，借款人 -> 区块高度
+        at_risk_since: StorageHashMap<AccountId, BlockNumber>,
+        // 有浮动利率借款或者固定期限借款的用户列表，方便清算机器人查找清算对象
+        borrower_list: StorageVec<AccountId>,
+        // 借款用户在 borrower_list 中的位置，借款用户 -> 下标
+        borrower_positions: StorageHashMap<AccountId, u32>,
+        // 清算后已经没有质押但仍有借款的坏账，借款人 -> 记录时的借款数量
+        bad_debts: StorageHashMap<AccountId, Balance>,
+        // 尚未核销的坏账总和
+        total_bad_debt: Balance,
+        // 等待接受管理权的新管理者，两步转移管理权
+        pending_owner: Option<AccountId>,
+        // 可以修改 config 和质押币种风险参数的治理账号，部署时为管理者，之后可以交给时间锁合约
+        governance: AccountId,
+        // 全局的利率、费率和上限等参数
+        config: Config,
+        // 各个借款等级的借款上限和利率折扣
+        tier_configs: StorageHashMap<Tier, TierConfig>,
+        // 用户的借款等级，没有设置等级的用户不受等级限制
+        account_tiers: StorageHashMap<AccountId, Tier>,
+        // 正在拍卖的质押，(借款人, 质押币种) -> 拍卖
+        auctions: StorageHashMap<(AccountId, AccountId), Auction>,
+        // 稳定利率借款，借款人 -> 借款，利率在借款时按当时的浮动利率确定
+        stable_debts: StorageHashMap<AccountId, StableDebt>,
+        // 稳定利率借款的总和，只在单个借款结算时累加利息
+        total_stable_debt: Balance,
+        // 用户用质押归还借款后由协议持有的质押代币，币种 -> 数量
+        protocol_collateral: StorageHashMap<AccountId, Balance>,
+        // 清算人质押的基础代币，不计入可借出的资金
+        keeper_stakes: StorageHashMap<AccountId, Balance>,
+        // 所有清算人质押的总和
+        total_keeper_stake: Balance,
+        // 借款仓位转让的授权，仓位所属的用户 -> 被授权转让该仓位的账号
+        position_approvals: StorageHashMap<AccountId, AccountId>,
+        // 接收借款仓位的同意，接收者 -> 同意接收其仓位的用户
+        position_acceptances: StorageHashMap<AccountId, AccountId>,
+        // 隔离模式的质押币种，币种 -> 使用该币种质押的所有借款的上限
+        isolation_ceilings: StorageHashMap<AccountId, Balance>,
+        // 使用隔离币种质押的借款总和，币种 -> 数量，只记录借出的数量，不包含利息
+        isolated_totals: StorageHashMap<AccountId, Balance>,
+        // 用户在隔离模式下的借款，用户 -> (隔离币种, 数量)
+        isolated_debts: StorageHashMap<AccountId, (AccountId, Balance)>,
+        // 流动性挖矿的奖励代币，None 表示没有开启奖励
+        reward_token: Option<AccountId>,
+        // 每个区块分给存款人和借款人的奖励代币数量
+        supply_reward_speed: Balance,
+        borrow_reward_speed: Balance,
+        // 每一个份额或者每一个借款累积获得的奖励，放大了 INDEX_PRECISION 倍
+        supply_reward_index: u128,
+        borrow_reward_index: u128,
+        // 上次累积奖励指数的区块
+        last_reward_block: BlockNumber,
+        // 用户上次结算奖励时的奖励指数
+        supplier_reward_indexes: StorageHashMap<AccountId, u128>,
+        borrower_reward_indexes: StorageHashMap<AccountId, u128>,
+        // 用户已经结算但还没有领取的奖励
+        accrued_rewards: StorageHashMap<AccountId, Balance>,
+        // 通过链扩展读取价格时质押币种在运行时预言机中的编号：质押币种 -> 编号
+        price_assets: StorageHashMap<AccountId, u32>,
+        // 用质押归还、尚未补回资金池的借款数量，补回之前计入资金池价值，存款人不承担这部分损失
+        pending_replenishment: Balance,
+        // 管理者转入、尚未被领取的奖励代币，领取的奖励不能超过这个数量
+        reward_balance: Balance,
+    }
+
+    // 管理者充值了基础代币
+    #[ink(event)]
+    pub struct Recharged {
+        #[ink(topic)]
+        owner: AccountId,
+        amount: Balance,
+    }
+
+    // 管理者把卖出协议持有的质押得到的基础代币补回了资金池
+    #[ink(event)]
+    pub struct Replenished {
+        #[ink(topic)]
+        owner: AccountId,
+        amount: Balance,
+    }
+
+    // 管理者提议把管理权转移给新的账号，proposed 为 None 表示撤销提议
+    #[ink(event)]
+    pub struct OwnershipProposed {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        proposed: Option<AccountId>,
+    }
+
+    // 新的管理者接受了管理权
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        previous_owner: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    // 管理者、治理账号或者守护者修改了参数，token 为参数对应的币种
+    #[ink(event)]
+    pub struct ParamsUpdated {
+        #[ink(topic)]
+        param: Param,
+        #[ink(topic)]
+        token: Option<AccountId>,
+        #[ink(topic)]
+        updated_by: AccountId,
+    }
+
+    // 用户质押了代币
+    #[ink(event)]
+    pub struct Pledged {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        token: AccountId,
+        amount: Balance,
+    }
+
+    // 用户借出了基础代币
+    #[ink(event)]
+    pub struct Borrowed {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        collateral_token: AccountId,
+        amount: Balance,
+    }
+
+    // 用户把一种质押代币换成了另一种
+    #[ink(event)]
+    pub struct CollateralSwapped {
+        #[ink(topic)]
+        account: AccountId,
+        from_token: AccountId,
+        to_token: AccountId,
+        from_amount: Balance,
+        to_amount: Balance,
+    }
+
+    // 用户归还了借款
+    #[ink(event)]
+    pub struct Repaid {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    // 其他账号替借款人归还了借款
+    #[ink(event)]
+    pub struct RepaidOnBehalf {
+        #[ink(topic)]
+        payer: AccountId,
+        #[ink(topic)]
+        borrower: AccountId,
+        amount: Balance,
+    }
+
+    // 用户取回了质押的代币
+    #[ink(event)]
+    pub struct CollateralWithdrawn {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        token: AccountId,
+        amount: Balance,
+    }
+
+    // 存款人存入了基础代币
+    #[ink(event)]
+    pub struct Supplied {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+        shares: Balance,
+    }
+
+    // 存款人取回了基础代币
+    #[ink(event)]
+    pub struct SupplyWithdrawn {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+        shares: Balance,
+    }
+
+    // 存款份额发生了转移，存入时 from 为 None，取回时 to 为 None
+    #[ink(event)]
+    pub struct SharesTransfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+    }
+
+    // 授权别人转让自己的存款份额
+    #[ink(event)]
+    pub struct SharesApproval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
+    }
+
+    // 委托人设置了被委托人的信用额度
+    #[ink(event)]
+    pub struct CreditApproval {
+        #[ink(topic)]
+        delegator: AccountId,
+        #[ink(topic)]
+        delegatee: AccountId,
+        amount: Balance,
+    }
+
+    // 被委托人使用委托人的质押借出了基础代币，债务记在委托人名下
+    #[ink(event)]
+    pub struct CreditBorrowed {
+        #[ink(topic)]
+        delegator: AccountId,
+        #[ink(topic)]
+        delegatee: AccountId,
+        collateral_token: AccountId,
+        amount: Balance,
+    }
+
+    // 被委托人替委托人归还了借款
+    #[ink(event)]
+    pub struct CreditRepaid {
+        #[ink(topic)]
+        delegator: AccountId,
+        #[ink(topic)]
+        delegatee: AccountId,
+        amount: Balance,
+    }
+
+    // 债务代币发生了变化，借款和累积利息时 from 为 None，还款时 to 为 None，债务代币不能转让
+    #[ink(event)]
+    pub struct DebtTransfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+    }
+
+    // 质押币种的风险参数，比例都以万分之一为单位
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct CollateralConfig {
+        // 借款时质押价值可以借出的比例，必须小于清算线，按最大比例借款后价格稍有下跌也不会立即被清算
+        pub collateral_factor_bps: u32,
+        // 清算线，质押价值乘以该比例低于借款时可以被清算
+        pub liquidation_threshold_bps: u32,
+        // 清算奖励，清算人获得的质押价值比归还的借款多出的比例
+        pub liquidation_bonus_bps: u32,
+    }
+
+    // 被修改的参数类型
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Param {
+        CollateralConfig,
+        CollateralRemoved,
+        Caps,
+        Config,
+        Governance,
+        PriceOracle,
+        LiquidationBonus,
+        Guardian,
+        Pause,
+        Tier,
+        Isolation,
+        Rewards,
+    }
+
+    // 全局参数，集中保存方便审计，只能由治理账号通过 update_config 整体修改
+    // 各个质押币种的质押率、清算参数和质押上限仍然按币种单独设置
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Config {
+        // 利率模型：资金利用率为 0 时每个区块的借款利率，以下利率和利用率都放大了 INDEX_PRECISION 倍
+        pub base_rate: u128,
+        // 利用率从 0 增加到最优利用率时，借款利率增加的数量
+        pub slope1: u128,
+        // 利用率从最优利用率增加到 100% 时，借款利率增加的数量
+        pub slope2: u128,
+        // 最优利用率，超过后利率按 slope2 快速上升
+        pub optimal_utilization: u128,
+        // 借款利息中归协议所有的比例，万分比
+        pub reserve_factor_bps: u32,
+        // 新的固定期限借款使用的每个区块利率
+        pub fixed_rate: u128,
+        // 逾期后滞纳金的基础费率，逾期越久每个区块收取的滞纳金越多
+        pub late_fee_rate: u128,
+        // 闪电贷手续费，万分比
+        pub flash_loan_fee_bps: u32,
+        // 仓位变得不健康之后需要等待多少个区块才能被清算，0 表示可以立即清算
+        pub grace_blocks: BlockNumber,
+        // 预言机价格的最长有效时间（毫秒），超过后视为无法获取价格
+        pub max_price_age: Timestamp,
+        // 基础代币的借款总额上限，None 表示不限制
+        pub borrow_cap: Option<Balance>,
+        // 资金池总价值的上限，None 表示不限制
+        pub supply_cap: Option<Balance>,
+        // 荷兰式拍卖清算持续的区块数，0 表示不使用拍卖，按固定的清算奖励清算
+        pub auction_blocks: BlockNumber,
+        // 拍卖开始时的价格相对预言机价格的比例，万分比
+        pub auction_start_bps: u32,
+        // 拍卖结束时的最低价格相对预言机价格的比例，万分比
+        pub auction_end_bps: u32,
+        // 稳定利率在借款时的浮动利率之上增加的数量，放大了 INDEX_PRECISION 倍
+        pub stable_rate_premium: u128,
+        // 成为清算人需要质押的最少基础代币数量，0 表示任何人都可以清算
+        pub min_keeper_stake: Balance,
+        // 清算人每次清算额外获得的奖励相对归还数量的比例，万分比，从协议储备金中支付
+        pub keeper_reward_bps: u32,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                base_rate: 0,
+                slope1: 0,
+                slope2: 0,
+                optimal_utilization: DEFAULT_OPTIMAL_UTILIZATION,
+                reserve_factor_bps: 0,
+                fixed_rate: 0,
+                late_fee_rate: 0,
+                flash_loan_fee_bps: 0,
+                grace_blocks: 0,
+                max_price_age: DEFAULT_MAX_PRICE_AGE,
+                borrow_cap: None,
+                supply_cap: None,
+                auction_blocks: 0,
+                auction_start_bps: 11_000,
+                auction_end_bps: 8_000,
+                stable_rate_premium: 0,
+                min_keeper_stake: 0,
+                keeper_reward_bps: 0,
+            }
+        }
+    }
+
+    // 借款的利率模式
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum RateMode {
+        // 按资金利用率浮动的利率
+        Variable,
+        // 借款时确定的稳定利率
+        Stable,
+    }
+
+    // 稳定利率借款，利息按单利计算，每次结算后并入 amount
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct StableDebt {
+        // 上次结算时包含利息的借款数量
+        pub amount: Balance,
+        // 每个区块的利率，放大了 INDEX_PRECISION 倍
+        pub rate: u128,
+        pub last_update_block: BlockNumber,
+    }
+
+    // 荷兰式拍卖，价格从 start_price 开始在 auction_blocks 个区块内线性下降到 end_price，之后保持不变
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct Auction {
+        // 剩余待拍卖的质押数量
+        pub amount: Balance,
+        pub start_block: BlockNumber,
+        pub end_block: BlockNumber,
+        // 1 个质押代币值多少基础代币，放大了 PRICE_PRECISION 倍
+        pub start_price: u128,
+        pub end_price: u128,
+    }
+
+    // 用户的借款等级，由管理者设置
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub enum Tier {
+        Bronze,
+        Silver,
+        Gold,
+    }
+
+    // 借款等级的参数
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct TierConfig {
+        // 浮动利率借款和固定期限借款加起来的上限
+        pub max_borrow: Balance,
+        // 利息的折扣，万分比
+        pub rate_discount_bps: u32,
+    }
+
+    // 可以单独暂停的功能
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum PauseAction {
+        // 借款
+        Borrow,
+        // 存款，包括管理者充值
+        Supply,
+        // 取回存款和质押
+        Withdraw,
+        // 清算
+        Liquidation,
+    }
+
+    // 各个功能的暂停状态，紧急关停后不能再借款，但仍然可以还款和取回质押
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct PauseState {
+        pub borrow_paused: bool,
+        pub supply_paused: bool,
+        pub withdraw_paused: bool,
+        pub liquidation_paused: bool,
+        pub emergency_shutdown: bool,
+    }
+
+    // 固定期限借款，本金和利率在借款时确定，到期后开始按逐渐增加的费率收取滞纳金
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout))]
+    pub struct FixedLoan {
+        pub principal: Balance,
+        // 每个区块的利率，放大了 INDEX_PRECISION 倍
+        pub rate: u128,
+        pub start_block: BlockNumber,
+        pub maturity_block: BlockNumber,
+        // 已经归还的数量，先归还本金
+        pub repaid: Balance,
+    }
+
+    // 用户的借款仓位，方便前端一次查询全部获取
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Position {
+        // 各个质押币种的质押数量
+        pub collaterals: Vec<(AccountId, Balance)>,
+        // 包含截至当前区块利息的借款数量
+        pub debt: Balance,
+        // 健康系数，放大了 INDEX_PRECISION 倍
+        pub health_factor: u128,
+        // 还可以借出的基础代币数量
+        pub available_to_borrow: Balance,
+    }
+
+    // 用户借出了稳定利率借款
+    #[ink(event)]
+    pub struct StableBorrowed {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        collateral_token: AccountId,
+        amount: Balance,
+        rate: u128,
+    }
+
+    // 用户切换了借款的利率模式，amount 为切换的借款数量
+    #[ink(event)]
+    pub struct RateModeSwapped {
+        #[ink(topic)]
+        account: AccountId,
+        to_mode: RateMode,
+        amount: Balance,
+    }
+
+    // 用户借出了一笔固定期限借款
+    #[ink(event)]
+    pub struct FixedBorrowed {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+        rate: u128,
+        maturity_block: BlockNumber,
+    }
+
+    // 用户归还了固定期限借款
+    #[ink(event)]
+    pub struct FixedRepaid {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+        remaining: Balance,
+    }
+
+    // 一笔闪电贷已经归还
+    #[ink(event)]
+    pub struct FlashLoan {
+        #[ink(topic)]
+        initiator: AccountId,
+        #[ink(topic)]
+        receiver: AccountId,
+        amount: Balance,
+        fee: Balance,
+    }
+
+    // 借款人的仓位变得不健康，开始清算前的宽限期计时
+    #[ink(event)]
+    pub struct PositionAtRisk {
+        #[ink(topic)]
+        borrower: AccountId,
+        health_factor: u128,
+        liquidatable_at: BlockNumber,
+    }
+
+    // 借款人的质押进入了荷兰式拍卖
+    #[ink(event)]
+    pub struct AuctionStarted {
+        #[ink(topic)]
+        borrower: AccountId,
+        #[ink(topic)]
+        collateral_token: AccountId,
+        amount: Balance,
+        start_price: u128,
+        end_price: u128,
+        end_block: BlockNumber,
+    }
+
+    // 买家按拍卖的当前价格买入了质押，cost 用于归还借款人的借款
+    #[ink(event)]
+    pub struct AuctionBid {
+        #[ink(topic)]
+        borrower: AccountId,
+        #[ink(topic)]
+        buyer: AccountId,
+        collateral_token: AccountId,
+        amount: Balance,
+        cost: Balance,
+    }
+
+    // 拍卖结束，returned 为退回借款人质押的数量，surplus 为转给借款人的多余基础代币
+    #[ink(event)]
+    pub struct AuctionEnded {
+        #[ink(topic)]
+        borrower: AccountId,
+        #[ink(topic)]
+        collateral_token: AccountId,
+        returned: Balance,
+        surplus: Balance,
+    }
+
+    // 借款人被清算
+    #[ink(event)]
+    pub struct Liquidated {
+        #[ink(topic)]
+        liquidator: AccountId,
+        #[ink(topic)]
+        borrower: AccountId,
+        #[ink(topic)]
+        collateral_token: AccountId,
+        repay_amount: Balance,
+        seized_amount: Balance,
+        liquidation_bonus_bps: u32,
+    }
+
+    // 借款人的质押已经全部被清算，剩余的借款记为坏账
+    #[ink(event)]
+    pub struct BadDebtRecorded {
+        #[ink(topic)]
+        borrower: AccountId,
+        amount: Balance,
+    }
+
+    // 坏账被核销，损失由所有存款人按份额分摊
+    #[ink(event)]
+    pub struct BadDebtWrittenOff {
+        #[ink(topic)]
+        borrower: AccountId,
+        amount: Balance,
+    }
+
+    // 用户用质押归还了借款
+    #[ink(event)]
+    pub struct RepaidWithCollateral {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        collateral_token: AccountId,
+        collateral_amount: Balance,
+        repaid: Balance,
+    }
+
+    // 管理者取出了协议持有的质押代币
+    #[ink(event)]
+    pub struct ProtocolCollateralWithdrawn {
+        #[ink(topic)]
+        token: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    // 清算人增加了质押，total 为增加后的质押数量
+    #[ink(event)]
+    pub struct KeeperStaked {
+        #[ink(topic)]
+        keeper: AccountId,
+        amount: Balance,
+        total: Balance,
+    }
+
+    // 清算人取回了质押，total 为取回后的质押数量
+    #[ink(event)]
+    pub struct KeeperUnstaked {
+        #[ink(topic)]
+        keeper: AccountId,
+        amount: Balance,
+        total: Balance,
+    }
+
+    // 清算人的质押被罚没，罚没的基础代币计入协议储备金
+    #[ink(event)]
+    pub struct KeeperSlashed {
+        #[ink(topic)]
+        keeper: AccountId,
+        amount: Balance,
+    }
+
+    // 清算人获得了协议支付的清算奖励
+    #[ink(event)]
+    pub struct KeeperRewarded {
+        #[ink(topic)]
+        keeper: AccountId,
+        #[ink(topic)]
+        borrower: AccountId,
+        amount: Balance,
     }
+
+    // 借款仓位从 from 转让给了 to
+    #[ink(event)]
+    pub struct PositionTransfer {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+    }
+
+    // 用户授权 approved 转让自己的借款仓位，approved 为 None 表示取消授权
+    #[ink(event)]
+    pub struct PositionApproval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        approved: Option<AccountId>,
+    }
+
+    // 用户同意接收 from 的借款仓位，from 为 None 表示取消同意
+    #[ink(event)]
+    pub struct PositionAcceptance {
+        #[ink(topic)]
+        to: AccountId,
+        #[ink(topic)]
+        from: Option<AccountId>,
+    }
+
+    // 用户领取了流动性挖矿的奖励
+    #[ink(event)]
+    pub struct RewardsClaimed {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        reward_token: AccountId,
+        amount: Balance,
+    }
+
+    // 管理者转入了流动性挖矿的奖励代币
+    #[ink(event)]
+    pub struct RewardsFunded {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        reward_token: AccountId,
+        amount: Balance,
+    }
+
+    // 管理者取出了协议储备金
+    #[ink(event)]
+    pub struct ReservesWithdrawn {
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    // 借款比例的分母，比例以万分之一为单位
+    const RATIO_DENOMINATOR: Balance = 10_000;
+
+    // 价格的放大倍数，价格为 PRICE_PRECISION 表示 1 个质押代币值 1 个基础代币
+    const PRICE_PRECISION: u128 = 1_000_000_000_000;
+
+    // 代币合约 transfer(to, value) 消息的 selector，即 BLAKE2("transfer") 的前 4 个字节
+    // 基础代币和质押代币只需要提供与 erc20 合约相同的 transfer 和 transfer_from 消息，不限定具体的合约实现
+    #[cfg(not(test))]
+    const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+    // 代币合约 transfer_from(from, to, value) 消息的 selector，即 BLAKE2("transfer_from") 的前 4 个字节
+    #[cfg(not(test))]
+    const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+    // 预言机合约 PriceOracle::price 消息的 selector，即 BLAKE2("PriceOracle::price") 的前 4 个字节
+    const PRICE_SELECTOR: [u8; 4] = [0xB6, 0x59, 0x81, 0x4E];
+
+    // 闪电贷接收合约 FlashLoanReceiver::on_flash_loan 消息的 selector，即 BLAKE2("FlashLoanReceiver::on_flash_loan") 的前 4 个字节
+    const ON_FLASH_LOAN_SELECTOR: [u8; 4] = [0x6E, 0x08, 0xE1, 0x22];
+
+    // 返回 total 是否超过了上限 cap，None 表示不限制
+    fn exceeds(cap: Option<Balance>, total: Balance) -> bool {
+        match cap {
+            Some(cap) => total > cap,
+            None => false,
+        }
+    }
+
+    // 默认的预言机价格有效时间，1 小时
+    const DEFAULT_MAX_PRICE_AGE: Timestamp = 3_600_000;
+
+    // 借款指数和利率的放大倍数
+    const INDEX_PRECISION: u128 = 1_000_000_000_000;
+
+    // 默认的最优利用率，80%
+    const DEFAULT_OPTIMAL_UTILIZATION: u128 = INDEX_PRECISION / 5 * 4;
+
     // 定义返回类型，当有返回值也可能返回错误的函数，需要用 Result 类型返回
     pub type Result<T> = core::result::Result<T, Error>;
 
@@ -36,6 +853,94 @@ mod loan {
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub enum Error {
         OnlyForOwner,
+        // 数量不能为 0
+        ZeroAmount,
+        // 代币合约转账失败，比如余额或授权不足
+        TokenTransferFailed,
+        // 质押不足，借款后会超过最大借款比例
+        InsufficientCollateral,
+        // 资金池中剩余可借出的数量不足
+        InsufficientLiquidity,
+        // 没有需要归还的借款
+        NoDebt,
+        // 取回的数量超过了质押数量
+        InsufficientPledge,
+        // 取回后剩余的质押不足以覆盖借款
+        WouldBeUndercollateralized,
+        // 不支持该币种作为质押
+        UnsupportedCollateral,
+        // 风险参数无效，借款比例必须大于 0 且小于清算线，清算线和清算奖励不能超过 10000
+        InvalidRatio,
+        // 最优利用率必须大于 0 且小于 100%
+        InvalidUtilization,
+        // 取回的数量超过了存款
+        InsufficientSupply,
+        // 份额的授权数量不足
+        InsufficientShareAllowance,
+        // 超过了币种的借款或存入上限
+        CapReached,
+        // 借款人的健康系数不低于 1，不能被清算
+        PositionHealthy,
+        // 不能清算自己
+        SelfLiquidation,
+        // 取出的数量超过了协议储备金
+        InsufficientReserves,
+        // 只能管理者或者守护者调用
+        OnlyForGuardian,
+        // 该功能已经暂停
+        Paused,
+        // 已经紧急关停，不能再借款
+        EmergencyShutdown,
+        // 已经有一笔没有还清的固定期限借款
+        FixedLoanExists,
+        // 借款期限必须大于 0
+        InvalidTerm,
+        // 闪电贷的接收合约拒绝了借款或者没有归还借款和手续费
+        FlashLoanNotRepaid,
+        // 信用委托额度不足
+        InsufficientCredit,
+        // 仓位还在清算前的宽限期内，或者还没有开始计时
+        GracePeriodActive,
+        // 换入和换出的质押币种相同
+        SameCollateral,
+        // 用户没有坏账或者仍有质押
+        NoBadDebt,
+        // 调用者不是等待接受管理权的账号
+        NotPendingOwner,
+        // 超过了借款等级的借款上限
+        TierLimitExceeded,
+        // 只有治理账号可以修改参数
+        OnlyForGovernance,
+        // 没有开启拍卖清算
+        AuctionDisabled,
+        // 开启了拍卖清算，不能按固定的清算奖励清算
+        AuctionModeActive,
+        // 该质押已经在拍卖中，或者借款人还有借款不能结束拍卖
+        AuctionExists,
+        // 没有进行中的拍卖
+        NoAuction,
+        // 无法获取质押币种的价格
+        PriceUnavailable,
+        // 调用者质押的基础代币不足，不能作为清算人
+        NotKeeper,
+        // 取回或罚没的数量超过了清算人的质押
+        InsufficientStake,
+        // 用户没有借款也没有质押，没有可以转让的仓位
+        NoPosition,
+        // 调用者不是借款仓位所属的用户或被授权的账号
+        NotPositionOwner,
+        // 接收者已经有借款或质押，不能接收借款仓位
+        PositionExists,
+        // 接收者没有同意接收该借款仓位
+        PositionNotAccepted,
+        // 隔离币种不能和其他币种同时质押
+        IsolationViolation,
+        // 没有设置奖励代币
+        RewardsDisabled,
+        // 奖励代币不能是基础代币，已有奖励余额时不能更换奖励代币
+        InvalidRewardToken,
+        // 奖励余额已经用完，暂时不能领取奖励
+        InsufficientRewards,
     }
 
     impl Loan {
@@ -48,9 +953,58 @@ mod loan {
                 base_token_accountid: token,
                 borrowings_balance: 0,
                 total_borrowings: 0,
-                min_collateral_ratio: StorageHashMap::new(),
+                collateral_configs: StorageHashMap::new(),
                 pledges: StorageHashMap::new(),
                 borrowings: StorageHashMap::new(),
+                price_oracle: None,
+                borrow_index: INDEX_PRECISION,
+                last_accrual_block: Self::env().block_number(),
+                account_borrow_index: StorageHashMap::new(),
+                supply_shares: StorageHashMap::new(),
+                total_supply_shares: 0,
+                share_allowances: StorageHashMap::new(),
+                total_pledged: StorageHashMap::new(),
+                supply_caps: StorageHashMap::new(),
+                protocol_reserves: 0,
+                guardian: None,
+                pause_state: PauseState::default(),
+                fixed_loans: StorageHashMap::new(),
+                total_fixed_principal: 0,
+                credit_allowances: StorageHashMap::new(),
+                delegated_debts: StorageHashMap::new(),
+                at_risk_since: StorageHashMap::new(),
+                borrower_list: StorageVec::new(),
+                borrower_positions: StorageHashMap::new(),
+                bad_debts: StorageHashMap::new(),
+                total_bad_debt: 0,
+                pending_owner: None,
+                governance: caller,
+                config: Config::default(),
+                tier_configs: StorageHashMap::new(),
+                account_tiers: StorageHashMap::new(),
+                auctions: StorageHashMap::new(),
+                stable_debts: StorageHashMap::new(),
+                total_stable_debt: 0,
+                protocol_collateral: StorageHashMap::new(),
+                keeper_stakes: StorageHashMap::new(),
+                total_keeper_stake: 0,
+                position_approvals: StorageHashMap::new(),
+                position_acceptances: StorageHashMap::new(),
+                isolation_ceilings: StorageHashMap::new(),
+                isolated_totals: StorageHashMap::new(),
+                isolated_debts: StorageHashMap::new(),
+                reward_token: None,
+                supply_reward_speed: 0,
+                borrow_reward_speed: 0,
+                supply_reward_index: 0,
+                borrow_reward_index: 0,
+                last_reward_block: Self::env().block_number(),
+                supplier_reward_indexes: StorageHashMap::new(),
+                borrower_reward_indexes: StorageHashMap::new(),
+                accrued_rewards: StorageHashMap::new(),
+                price_assets: StorageHashMap::new(),
+                pending_replenishment: 0,
+                reward_balance: 0,
             }
         }
 
@@ -66,55 +1020,3751 @@ mod loan {
         }
 
         // Rechage base token for borrowing
+        // 管理者充值的基础代币与其他存款人一样按份额记账
         #[ink(message)]
         pub fn recharge_for_borrowing(&mut self, amount: Balance) -> Result<()> {
             let caller = Self::env().caller();
             if caller != self.owner {
                 return Err(Error::OnlyForOwner)
             }
-            let mut base_token: Erc20 = FromAccountId::from_account_id( self.base_token_accountid );
+            self.supply_from(caller, amount)?;
+            self.env().emit_event( Recharged{
+                owner : caller,
+                amount,
+            });
+            Ok(())
+        }
 
-            let self_accountid = Self::env().account_id();
+        // 存入基础代币供其他人借款，按当前的资金池价值换算成份额，之后随借款利息一起增长
+        #[ink(message)]
+        pub fn supply(&mut self, amount: Balance) -> Result<()> {
+            let caller = Self::env().caller();
+            self.supply_from(caller, amount)
+        }
 
-            let message = format!("self_accountid =  {:?}", self_accountid);
-            ink_env::debug_println(&message);
+        // 取回存入的基础代币和获得的利息，不能超过资金池中剩余可借出的数量
+        #[ink(message)]
+        pub fn withdraw_supply(&mut self, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let caller = Self::env().caller();
+            self.accrue_interest();
+            let pool_value = self.pool_value();
+            if pool_value == 0 {
+                return Err(Error::InsufficientSupply)
+            }
+            // 份额向上取整，避免取出的代币多于份额对应的价值
+            let shares = (amount * self.total_supply_shares).div_ceil(pool_value);
+            self.redeem_from(caller, shares, amount)
+        }
 
-            let re = base_token.transfer_from( caller, self_accountid, amount);
+        // 按当前的兑换比例销毁指定数量的份额，取回对应的基础代币
+        #[ink(message)]
+        pub fn redeem(&mut self, shares: Balance) -> Result<()> {
+            let caller = Self::env().caller();
+            self.accrue_interest();
+            let amount = self.shares_to_amount(shares);
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.redeem_from(caller, shares, amount)
+        }
 
-            let message = format!("Return =  {:?}", re);
-            ink_env::debug_println(&message);
+        // 内部函数，销毁存款人的 shares 份额，并转给他 amount 数量的基础代币
+        fn redeem_from(&mut self, caller: AccountId, shares: Balance, amount: Balance) -> Result<()> {
+            self.ensure_not_paused(PauseAction::Withdraw)?;
+            let supplier_shares = self.supply_shares_of(caller);
+            if shares > supplier_shares {
+                return Err(Error::InsufficientSupply)
+            }
+            if amount > self.borrowings_balance {
+                return Err(Error::InsufficientLiquidity)
+            }
+            self.transfer_out(self.base_token_accountid, caller, amount)?;
+            self.distribute_supply_reward(caller);
 
+            if supplier_shares == shares {
+                self.supply_shares.take(&caller);
+            } else {
+                self.supply_shares.insert(caller, supplier_shares - shares);
+            }
+            self.total_supply_shares -= shares;
+            self.borrowings_balance -= amount;
 
-            self.borrowings_balance = self.borrowings_balance + amount;
-            
+            self.env().emit_event( SupplyWithdrawn{
+                account : caller,
+                amount,
+                shares,
+            });
+            self.env().emit_event( SharesTransfer{
+                from : Some(caller),
+                to : None,
+                value : shares,
+            });
             Ok(())
         }
 
-    }
+        // 返回份额的总量
+        #[ink(message)]
+        pub fn total_supply_shares(&self) -> Balance {
+            self.total_supply_shares
+        }
 
-    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
-    /// module and test functions are marked with a `#[test]` attribute.
-    /// The below code is technically just normal Rust code.
-    #[cfg(test)]
-    mod tests {
-        /// Imports all the definitions from the outer scope so we can use them here.
-        use super::*;
+        // 返回 1 个份额可以兑换多少基础代币，放大了 INDEX_PRECISION 倍
+        #[ink(message)]
+        pub fn exchange_rate(&self) -> u128 {
+            if self.total_supply_shares == 0 {
+                return INDEX_PRECISION
+            }
+            self.pool_value() * INDEX_PRECISION / self.total_supply_shares
+        }
+
+        // 内部函数，按当前的资金池价值计算份额对应的基础代币数量
+        fn shares_to_amount(&self, shares: Balance) -> Balance {
+            if self.total_supply_shares == 0 {
+                return 0
+            }
+            shares * self.pool_value() / self.total_supply_shares
+        }
+
+        // 转让自己的存款份额
+        #[ink(message)]
+        pub fn transfer_shares(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            let caller = Self::env().caller();
+            self.transfer_shares_from_to(caller, to, value)
+        }
 
-        /// We test if the default constructor does its job.
-        #[test]
-        fn default_works() {
-            let loan = Loan::new();
-            assert_eq!()
+        // 授权别人可以转让自己的存款份额
+        #[ink(message)]
+        pub fn approve_shares(&mut self, spender: AccountId, value: Balance) -> Result<()> {
+            let owner = Self::env().caller();
+            self.share_allowances.insert((owner, spender), value);
+            self.env().emit_event( SharesApproval{
+                owner,
+                spender,
+                value,
+            });
+            Ok(())
+        }
+
+        // 返回 owner 授权给 spender 的份额数量
+        #[ink(message)]
+        pub fn share_allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.share_allowances.get(&(owner, spender)).copied().unwrap_or(0)
+        }
+
+        // 使用授权转让 from 的存款份额
+        #[ink(message)]
+        pub fn transfer_shares_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<()> {
+            let caller = Self::env().caller();
+            let allowance = self.share_allowance(from, caller);
+            if allowance < value {
+                return Err(Error::InsufficientShareAllowance)
+            }
+            self.transfer_shares_from_to(from, to, value)?;
+            self.share_allowances.insert((from, caller), allowance - value);
+            Ok(())
+        }
+
+        // 内部函数，在两个账号之间转移存款份额
+        fn transfer_shares_from_to(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<()> {
+            let from_shares = self.supply_shares_of(from);
+            if from_shares < value {
+                return Err(Error::InsufficientSupply)
+            }
+            self.distribute_supply_reward(from);
+            self.distribute_supply_reward(to);
+            if from_shares == value {
+                self.supply_shares.take(&from);
+            } else {
+                self.supply_shares.insert(from, from_shares - value);
+            }
+            let to_shares = self.supply_shares_of(to);
+            self.supply_shares.insert(to, to_shares + value);
+            self.env().emit_event( SharesTransfer{
+                from : Some(from),
+                to : Some(to),
+                value,
+            });
+            Ok(())
+        }
+
+        // 返回存款人持有的份额
+        #[ink(message)]
+        pub fn supply_shares_of(&self, account: AccountId) -> Balance {
+            self.supply_shares.get(&account).copied().unwrap_or(0)
+        }
+
+        // 返回存款人的份额当前对应的基础代币数量，包含已经获得的利息
+        #[ink(message)]
+        pub fn supply_balance_of(&self, account: AccountId) -> Balance {
+            self.shares_to_amount(self.supply_shares_of(account))
+        }
+
+        // 内部函数，返回属于存款人的资金池总价值，即剩余可借出的数量加上包含利息的总借款和固定期限借款的本金，再减去协议储备金
+        fn pool_value(&self) -> Balance {
+            (self.borrowings_balance + self.total_debt() + self.pending_replenishment).saturating_sub(self.protocol_reserves)
+        }
+
+        // 内部函数，通过 transfer_from 从存款人转入基础代币，并按资金池价值给存款人增加份额
+        fn supply_from(&mut self, supplier: AccountId, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.ensure_not_paused(PauseAction::Supply)?;
+            self.accrue_interest();
+            let pool_value = self.pool_value();
+            if exceeds(self.config.supply_cap, pool_value + amount) {
+                return Err(Error::CapReached)
+            }
+            let shares = if self.total_supply_shares == 0 || pool_value == 0 {
+                amount
+            } else {
+                amount * self.total_supply_shares / pool_value
+            };
+            if shares == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.transfer_in(self.base_token_accountid, supplier, amount)?;
+            self.distribute_supply_reward(supplier);
+
+            let supplier_shares = self.supply_shares_of(supplier);
+            self.supply_shares.insert(supplier, supplier_shares + shares);
+            self.total_supply_shares += shares;
+            self.borrowings_balance += amount;
+
+            self.env().emit_event( Supplied{
+                account : supplier,
+                amount,
+                shares,
+            });
+            self.env().emit_event( SharesTransfer{
+                from : None,
+                to : Some(supplier),
+                value : shares,
+            });
+            Ok(())
+        }
+
+        // 设置质押币种的风险参数，同时把该币种加入支持的质押币种，只能治理账号调用
+        #[ink(message)]
+        pub fn set_collateral_config(&mut self, token: AccountId, config: CollateralConfig) -> Result<()> {
+            self.ensure_governance()?;
+            if config.collateral_factor_bps == 0
+                || config.collateral_factor_bps >= config.liquidation_threshold_bps
+                || config.liquidation_threshold_bps as Balance > RATIO_DENOMINATOR
+                || config.liquidation_bonus_bps as Balance > RATIO_DENOMINATOR
+            {
+                return Err(Error::InvalidRatio)
+            }
+            self.collateral_configs.insert(token, config);
+            self.emit_params_updated(Param::CollateralConfig, Some(token));
+            Ok(())
+        }
+
+        // 不再支持该币种作为质押，已有的质押仍可在没有借款时取回，只能治理账号调用
+        #[ink(message)]
+        pub fn remove_collateral(&mut self, token: AccountId) -> Result<()> {
+            self.ensure_governance()?;
+            if self.collateral_configs.take(&token).is_none() {
+                return Err(Error::UnsupportedCollateral)
+            }
+            self.emit_params_updated(Param::CollateralRemoved, Some(token));
+            Ok(())
+        }
+
+        // 返回支持的质押币种及其风险参数
+        #[ink(message)]
+        pub fn supported_collaterals(&self) -> Vec<(AccountId, CollateralConfig)> {
+            self.collateral_configs
+                .iter()
+                .map(|(token, config)| (*token, *config))
+                .collect()
+        }
+
+        // 返回全局参数
+        #[ink(message)]
+        pub fn config(&self) -> Config {
+            self.config
+        }
+
+        // 整体替换全局参数，修改前先按旧的利率累积利息，只能治理账号调用
+        #[ink(message)]
+        pub fn update_config(&mut self, config: Config) -> Result<()> {
+            self.ensure_governance()?;
+            if config.optimal_utilization == 0 || config.optimal_utilization >= INDEX_PRECISION {
+                return Err(Error::InvalidUtilization)
+            }
+            if config.reserve_factor_bps as Balance > RATIO_DENOMINATOR
+                || config.flash_loan_fee_bps as Balance > RATIO_DENOMINATOR
+                || config.keeper_reward_bps as Balance > RATIO_DENOMINATOR
+                || config.auction_end_bps > config.auction_start_bps
+            {
+                return Err(Error::InvalidRatio)
+            }
+            self.accrue_interest();
+            self.config = config;
+            self.emit_params_updated(Param::Config, None);
+            Ok(())
+        }
+
+        // 返回治理账号
+        #[ink(message)]
+        pub fn governance(&self) -> AccountId {
+            self.governance
+        }
+
+        // 把治理权交给新的账号，例如时间锁合约，只能当前的治理账号调用
+        #[ink(message)]
+        pub fn set_governance(&mut self, governance: AccountId) -> Result<()> {
+            self.ensure_governance()?;
+            self.governance = governance;
+            self.emit_params_updated(Param::Governance, None);
+            Ok(())
+        }
 
+        // 内部函数，确认调用者是治理账号
+        fn ensure_governance(&self) -> Result<()> {
+            if Self::env().caller() != self.governance {
+                return Err(Error::OnlyForGovernance)
+            }
+            Ok(())
         }
 
-        /// We test a simple use case of our contract.
-        #[test]
-        fn it_works() {
-            let mut loan = Loan::new(false);
-            assert_eq!(loan.get(), false);
-            loan.flip();
-            assert_eq!(loan.get(), true);
+        // 返回守护者账号
+        #[ink(message)]
+        pub fn guardian(&self) -> Option<AccountId> {
+            self.guardian
+        }
+
+        // 设置守护者账号，None 表示取消，只能管理者调用
+        #[ink(message)]
+        pub fn set_guardian(&mut self, guardian: Option<AccountId>) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyForOwner)
+            }
+            self.guardian = guardian;
+            self.emit_params_updated(Param::Guardian, None);
+            Ok(())
+        }
+
+        // 返回合约管理者
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        // 返回等待接受管理权的账号
+        #[ink(message)]
+        pub fn pending_owner(&self) -> Option<AccountId> {
+            self.pending_owner
+        }
+
+        // 提议把管理权转移给新的账号，新账号调用 accept_owner 后才生效，None 表示撤销提议，只能管理者调用
+        #[ink(message)]
+        pub fn propose_owner(&mut self, new_owner: Option<AccountId>) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyForOwner)
+            }
+            self.pending_owner = new_owner;
+            self.env().emit_event( OwnershipProposed{
+                owner : caller,
+                proposed : new_owner,
+            });
+            Ok(())
+        }
+
+        // 接受管理权，只能被提议的账号调用
+        #[ink(message)]
+        pub fn accept_owner(&mut self) -> Result<()> {
+            let caller = Self::env().caller();
+            if self.pending_owner != Some(caller) {
+                return Err(Error::NotPendingOwner)
+            }
+            let previous_owner = self.owner;
+            self.owner = caller;
+            self.pending_owner = None;
+            self.env().emit_event( OwnershipTransferred{
+                previous_owner,
+                new_owner : caller,
+            });
+            Ok(())
+        }
+
+        // 返回各个功能的暂停状态
+        #[ink(message)]
+        pub fn pause_state(&self) -> PauseState {
+            self.pause_state
+        }
+
+        // 暂停或恢复指定的功能，只能管理者或者守护者调用
+        #[ink(message)]
+        pub fn set_paused(&mut self, action: PauseAction, paused: bool) -> Result<()> {
+            self.ensure_owner_or_guardian()?;
+            match action {
+                PauseAction::Borrow => self.pause_state.borrow_paused = paused,
+                PauseAction::Supply => self.pause_state.supply_paused = paused,
+                PauseAction::Withdraw => self.pause_state.withdraw_paused = paused,
+                PauseAction::Liquidation => self.pause_state.liquidation_paused = paused,
+            }
+            self.emit_params_updated(Param::Pause, None);
+            Ok(())
+        }
+
+        // 紧急关停，之后不能再借款，但仍然可以还款和取回质押，只能管理者或者守护者调用
+        #[ink(message)]
+        pub fn emergency_shutdown(&mut self) -> Result<()> {
+            self.ensure_owner_or_guardian()?;
+            self.pause_state.emergency_shutdown = true;
+            self.emit_params_updated(Param::Pause, None);
+            Ok(())
+        }
+
+        // 解除紧急关停，只能管理者调用
+        #[ink(message)]
+        pub fn end_emergency_shutdown(&mut self) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyForOwner)
+            }
+            self.pause_state.emergency_shutdown = false;
+            self.emit_params_updated(Param::Pause, None);
+            Ok(())
+        }
+
+        // 内部函数，触发参数修改事件
+        fn emit_params_updated(&self, param: Param, token: Option<AccountId>) {
+            let caller = Self::env().caller();
+            self.env().emit_event( ParamsUpdated{
+                param,
+                token,
+                updated_by : caller,
+            });
+        }
+
+        // 内部函数，确认调用者是管理者或者守护者
+        fn ensure_owner_or_guardian(&self) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner && Some(caller) != self.guardian {
+                return Err(Error::OnlyForGuardian)
+            }
+            Ok(())
+        }
+
+        // 内部函数，确认指定的功能没有被暂停
+        fn ensure_not_paused(&self, action: PauseAction) -> Result<()> {
+            let state = self.pause_state;
+            let paused = match action {
+                PauseAction::Borrow => state.borrow_paused,
+                PauseAction::Supply => state.supply_paused,
+                PauseAction::Withdraw => state.withdraw_paused,
+                PauseAction::Liquidation => state.liquidation_paused,
+            };
+            if paused {
+                return Err(Error::Paused)
+            }
+            if action == PauseAction::Borrow && state.emergency_shutdown {
+                return Err(Error::EmergencyShutdown)
+            }
+            Ok(())
+        }
+
+        // 返回质押币种的质押总量上限，None 表示不限制
+        #[ink(message)]
+        pub fn collateral_cap(&self, token: AccountId) -> Option<Balance> {
+            self.supply_caps.get(&token).copied()
+        }
+
+        // 设置质押币种的质押总量上限，None 表示不限制，只能治理账号调用
+        #[ink(message)]
+        pub fn set_collateral_cap(&mut self, token: AccountId, cap: Option<Balance>) -> Result<()> {
+            self.ensure_governance()?;
+            match cap {
+                Some(cap) => { self.supply_caps.insert(token, cap); }
+                None => { self.supply_caps.take(&token); }
+            }
+            self.emit_params_updated(Param::Caps, Some(token));
+            Ok(())
+        }
+
+        // 内部函数，返回 total 是否超过了质押币种的质押总量上限
+        fn exceeds_collateral_cap(&self, token: AccountId, total: Balance) -> bool {
+            exceeds(self.collateral_cap(token), total)
+        }
+
+        // 返回隔离币种的借款上限，None 表示该币种不是隔离模式
+        #[ink(message)]
+        pub fn isolation_ceiling(&self, token: AccountId) -> Option<Balance> {
+            self.isolation_ceilings.get(&token).copied()
+        }
+
+        // 设置质押币种为隔离模式，ceiling 为使用该币种质押的所有借款的上限，None 表示取消隔离模式
+        // 隔离币种只能单独质押，风险较高的币种可以用它限制可能产生的坏账，只能治理账号调用
+        #[ink(message)]
+        pub fn set_isolation(&mut self, token: AccountId, ceiling: Option<Balance>) -> Result<()> {
+            self.ensure_governance()?;
+            match ceiling {
+                Some(ceiling) => { self.isolation_ceilings.insert(token, ceiling); }
+                None => { self.isolation_ceilings.take(&token); }
+            }
+            self.emit_params_updated(Param::Isolation, Some(token));
+            Ok(())
+        }
+
+        // 返回使用隔离币种质押借出的数量之和
+        #[ink(message)]
+        pub fn isolated_total(&self, token: AccountId) -> Balance {
+            self.isolated_totals.get(&token).copied().unwrap_or(0)
+        }
+
+        // 返回用户质押的隔离币种
+        #[ink(message)]
+        pub fn isolation_asset_of(&self, account: AccountId) -> Option<AccountId> {
+            self.isolation_ceilings
+                .keys()
+                .find(|token| self.pledge_of(account, **token) > 0)
+                .copied()
+        }
+
+        // 内部函数，确认用户质押 token 后不会同时质押隔离币种和其他币种，ignore 为即将全部取回的币种
+        fn ensure_isolation(&self, account: AccountId, token: AccountId, ignore: Option<AccountId>) -> Result<()> {
+            let isolated = self.isolation_ceilings.contains_key(&token);
+            let violated = self.collateral_configs
+                .keys()
+                .filter(|other| **other != token && Some(**other) != ignore)
+                .filter(|other| self.pledge_of(account, **other) > 0)
+                .any(|other| isolated || self.isolation_ceilings.contains_key(other));
+            if violated {
+                return Err(Error::IsolationViolation)
+            }
+            Ok(())
+        }
+
+        // 内部函数，确认用户在隔离模式下再借出 amount 后不超过隔离币种的借款上限
+        fn ensure_isolation_ceiling(&self, account: AccountId, amount: Balance) -> Result<()> {
+            if let Some(token) = self.isolation_asset_of(account) {
+                if exceeds(self.isolation_ceiling(token), self.isolated_total(token) + amount) {
+                    return Err(Error::CapReached)
+                }
+            }
+            Ok(())
+        }
+
+        // 内部函数，用户在隔离模式下借出 amount 后记录隔离借款
+        fn add_isolated_debt(&mut self, account: AccountId, amount: Balance) {
+            if let Some(token) = self.isolation_asset_of(account) {
+                let debt = self.isolated_debts.get(&account).map(|(_, debt)| *debt).unwrap_or(0);
+                self.isolated_debts.insert(account, (token, debt + amount));
+                let total = self.isolated_total(token);
+                self.isolated_totals.insert(token, total + amount);
+            }
+        }
+
+        // 内部函数，用户归还 amount 后减少隔离借款，最多减少到 0
+        fn reduce_isolated_debt(&mut self, account: AccountId, amount: Balance) {
+            if let Some((token, debt)) = self.isolated_debts.get(&account).copied() {
+                let reduced = core::cmp::min(amount, debt);
+                if reduced == debt {
+                    self.isolated_debts.take(&account);
+                } else {
+                    self.isolated_debts.insert(account, (token, debt - reduced));
+                }
+                let total = self.isolated_total(token);
+                self.isolated_totals.insert(token, total.saturating_sub(reduced));
+            }
+        }
+
+        // 返回指定质押币种的质押总量
+        #[ink(message)]
+        pub fn total_pledged_of(&self, collateral_token: AccountId) -> Balance {
+            self.total_pledged.get(&collateral_token).copied().unwrap_or(0)
+        }
+
+        // 返回用户质押的指定代币数量
+        #[ink(message)]
+        pub fn pledge_of(&self, account: AccountId, collateral_token: AccountId) -> Balance {
+            self.pledges.get(&(account, collateral_token)).copied().unwrap_or(0)
+        }
+
+        // 质押代币，调用前需要先在质押代币合约中授权给本合约，通过 transfer_from 转入
+        #[ink(message)]
+        pub fn pledge(&mut self, collateral_token: AccountId, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            if !self.collateral_configs.contains_key(&collateral_token) {
+                return Err(Error::UnsupportedCollateral)
+            }
+            let total_pledged = self.total_pledged_of(collateral_token);
+            if self.exceeds_collateral_cap(collateral_token, total_pledged + amount) {
+                return Err(Error::CapReached)
+            }
+            let caller = Self::env().caller();
+            self.ensure_isolation(caller, collateral_token, None)?;
+            self.transfer_in(collateral_token, caller, amount)?;
+
+            let pledged = self.pledge_of(caller, collateral_token);
+            self.pledges.insert((caller, collateral_token), pledged + amount);
+            self.total_pledged.insert(collateral_token, total_pledged + amount);
+
+            self.env().emit_event( Pledged{
+                account : caller,
+                token : collateral_token,
+                amount,
+            });
+            self.clear_risk_if_healthy(caller);
+            Ok(())
+        }
+
+        // 取回质押的代币，有借款时剩余的所有质押仍需满足借款比例
+        #[ink(message)]
+        pub fn withdraw_collateral(&mut self, collateral_token: AccountId, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.ensure_not_paused(PauseAction::Withdraw)?;
+            let caller = Self::env().caller();
+            let pledged = self.pledge_of(caller, collateral_token);
+            if amount > pledged {
+                return Err(Error::InsufficientPledge)
+            }
+            let borrowed = self.settle_debt(caller) + self.other_debt_of(caller);
+            if borrowed > 0 {
+                let power = self.borrowing_power(caller)?
+                    - self.collateral_limit(collateral_token, pledged)?
+                    + self.collateral_limit(collateral_token, pledged - amount)?;
+                if power < borrowed {
+                    return Err(Error::WouldBeUndercollateralized)
+                }
+            }
+            self.transfer_out(collateral_token, caller, amount)?;
+
+            self.reduce_pledge(caller, collateral_token, amount);
+
+            self.env().emit_event( CollateralWithdrawn{
+                account : caller,
+                token : collateral_token,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 取回指定质押币种中超出部分的全部代币，取回后健康系数不低于 target_health_factor（放大了 INDEX_PRECISION 倍），
+        // 同时剩余的所有质押仍需满足借款比例，返回实际取回的数量
+        #[ink(message)]
+        pub fn withdraw_excess_collateral(&mut self, collateral_token: AccountId, target_health_factor: u128) -> Result<Balance> {
+            let caller = Self::env().caller();
+            let pledged = self.pledge_of(caller, collateral_token);
+            let debt = self.settle_debt(caller) + self.other_debt_of(caller);
+            let required = if debt == 0 {
+                0
+            } else {
+                self.required_pledge(caller, collateral_token, pledged, debt, target_health_factor)?
+            };
+            if required >= pledged {
+                return Err(Error::WouldBeUndercollateralized)
+            }
+            let amount = pledged - required;
+            self.withdraw_collateral(collateral_token, amount)?;
+            Ok(amount)
+        }
+
+        // 内部函数，返回借款 debt 时用户在指定质押币种上最少需要保留的质押数量，
+        // 需要同时满足借款比例和目标健康系数，各步计算都向上取整
+        fn required_pledge(&self, account: AccountId, collateral_token: AccountId, pledged: Balance, debt: Balance, target_health_factor: u128) -> Result<Balance> {
+            let config = match self.collateral_configs.get(&collateral_token) {
+                Some(config) => *config,
+                None => return Ok(0),
+            };
+            let price = self.price_of(collateral_token)?;
+            if price == 0 {
+                return Err(Error::PriceUnavailable)
+            }
+            let value = self.collateral_value(collateral_token, pledged)?;
+            // 其他质押币种提供的借款额度和清算线价值
+            let other_power = self.borrowing_power(account)? - self.collateral_limit(collateral_token, pledged)?;
+            let other_liquidation = self.liquidation_power(account)?
+                - value * config.liquidation_threshold_bps as Balance / RATIO_DENOMINATOR;
+
+            let needed_power = debt.saturating_sub(other_power);
+            let needed_liquidation = debt.saturating_mul(target_health_factor).div_ceil(INDEX_PRECISION)
+                .saturating_sub(other_liquidation);
+            let needed_value = core::cmp::max(
+                (needed_power * RATIO_DENOMINATOR).div_ceil(config.collateral_factor_bps as Balance),
+                (needed_liquidation * RATIO_DENOMINATOR).div_ceil(config.liquidation_threshold_bps as Balance),
+            );
+            Ok(needed_value.saturating_mul(PRICE_PRECISION).div_ceil(price))
+        }
+
+        // 在不还款的情况下更换质押币种，取回 amount 数量的 from_token，同时通过 transfer_from 转入等值的 to_token，
+        // 等值数量按当前价格计算并向上取整，更换后剩余的所有质押仍需满足借款比例
+        #[ink(message)]
+        pub fn swap_collateral(&mut self, from_token: AccountId, to_token: AccountId, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            if from_token == to_token {
+                return Err(Error::SameCollateral)
+            }
+            self.ensure_not_paused(PauseAction::Withdraw)?;
+            if !self.collateral_configs.contains_key(&to_token) {
+                return Err(Error::UnsupportedCollateral)
+            }
+            let caller = Self::env().caller();
+            let from_pledged = self.pledge_of(caller, from_token);
+            if amount > from_pledged {
+                return Err(Error::InsufficientPledge)
+            }
+            let to_price = self.price_of(to_token)?;
+            if to_price == 0 {
+                return Err(Error::PriceUnavailable)
+            }
+            let value = self.collateral_value(from_token, amount)?;
+            let to_amount = (value * PRICE_PRECISION).div_ceil(to_price);
+            if to_amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let to_total_pledged = self.total_pledged_of(to_token);
+            if self.exceeds_collateral_cap(to_token, to_total_pledged + to_amount) {
+                return Err(Error::CapReached)
+            }
+            let ignore = if amount == from_pledged { Some(from_token) } else { None };
+            self.ensure_isolation(caller, to_token, ignore)?;
+            let to_pledged = self.pledge_of(caller, to_token);
+            let borrowed = self.settle_debt(caller) + self.other_debt_of(caller);
+            if borrowed > 0 {
+                let power = self.borrowing_power(caller)?
+                    - self.collateral_limit(from_token, from_pledged)?
+                    + self.collateral_limit(from_token, from_pledged - amount)?
+                    - self.collateral_limit(to_token, to_pledged)?
+                    + self.collateral_limit(to_token, to_pledged + to_amount)?;
+                if power < borrowed {
+                    return Err(Error::WouldBeUndercollateralized)
+                }
+            }
+            self.pledges.insert((caller, to_token), to_pledged + to_amount);
+            self.total_pledged.insert(to_token, to_total_pledged + to_amount);
+            self.reduce_pledge(caller, from_token, amount);
+            // 先更新质押再转账，转出失败时已经转入的质押也会一起回滚
+            if self.transfer_in(to_token, caller, to_amount).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+            if self.transfer_out(from_token, caller, amount).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+
+            self.env().emit_event( CollateralSwapped{
+                account : caller,
+                from_token,
+                to_token,
+                from_amount : amount,
+                to_amount,
+            });
+            self.clear_risk_if_healthy(caller);
+            Ok(())
+        }
+
+        // 内部函数，减少用户质押的指定代币数量，调用前需要确认质押数量足够
+        fn reduce_pledge(&mut self, account: AccountId, collateral_token: AccountId, amount: Balance) {
+            let pledged = self.pledge_of(account, collateral_token);
+            if pledged == amount {
+                self.pledges.take(&(account, collateral_token));
+            } else {
+                self.pledges.insert((account, collateral_token), pledged - amount);
+            }
+            let total_pledged = self.total_pledged_of(collateral_token);
+            self.total_pledged.insert(collateral_token, total_pledged - amount);
+        }
+
+        // 返回用户的借款数量，为上次结算时的数量，不包含之后累积的利息
+        #[ink(message)]
+        pub fn borrowing_of(&self, account: AccountId) -> Balance {
+            self.borrowings.get(&account).copied().unwrap_or(0)
+        }
+
+        // 返回全局借款指数
+        #[ink(message)]
+        pub fn borrow_index(&self) -> u128 {
+            self.borrow_index
+        }
+
+        // 内部函数，返回浮动利率借款、稳定利率借款和固定期限借款本金的总和
+        fn total_debt(&self) -> Balance {
+            self.total_borrowings + self.total_stable_debt + self.total_fixed_principal
+        }
+
+        // 内部函数，返回资金利用率，即总借款 / (总借款 + 剩余可借出数量)
+        fn utilization(&self) -> u128 {
+            let borrowed = self.total_debt();
+            let total = borrowed + self.borrowings_balance;
+            if total == 0 {
+                return 0
+            }
+            borrowed * INDEX_PRECISION / total
+        }
+
+        // 内部函数，按利用率分段计算每个区块的借款利率，超过最优利用率后按 slope2 上升
+        fn borrow_rate(&self) -> u128 {
+            let utilization = self.utilization();
+            if utilization <= self.config.optimal_utilization {
+                self.config.base_rate + self.config.slope1 * utilization / self.config.optimal_utilization
+            } else {
+                let excess = utilization - self.config.optimal_utilization;
+                self.config.base_rate + self.config.slope1 + self.config.slope2 * excess / (INDEX_PRECISION - self.config.optimal_utilization)
+            }
+        }
+
+        // 返回当前每个区块的借款利率、存款利率和资金利用率，都放大了 INDEX_PRECISION 倍
+        #[ink(message)]
+        pub fn current_rates(&self) -> (u128, u128, u128) {
+            let utilization = self.utilization();
+            let borrow_rate = self.borrow_rate();
+            let supply_rate = borrow_rate * utilization / INDEX_PRECISION
+                * (RATIO_DENOMINATOR - self.config.reserve_factor_bps as Balance) / RATIO_DENOMINATOR;
+            (borrow_rate, supply_rate, utilization)
+        }
+
+        // 内部函数，按上次累积以来经过的区块数累积利息，更新借款指数和总借款数量
+        // 每次改变利用率之前都会先累积利息，所以这段时间内的利率都等于当前的利率
+        fn accrue_interest(&mut self) {
+            let block_number = self.env().block_number();
+            let blocks = block_number - self.last_accrual_block;
+            if blocks == 0 {
+                return
+            }
+            let interest_factor = self.borrow_rate() * blocks as u128;
+            let interest = self.total_borrowings * interest_factor / INDEX_PRECISION;
+            self.total_borrowings += interest;
+            self.protocol_reserves += interest * self.config.reserve_factor_bps as Balance / RATIO_DENOMINATOR;
+            self.borrow_index = self.borrow_index + self.borrow_index * interest_factor / INDEX_PRECISION;
+            self.last_accrual_block = block_number;
+        }
+
+        // 返回协议储备金
+        #[ink(message)]
+        pub fn reserves(&self) -> Balance {
+            self.protocol_reserves
+        }
+
+        // 取出协议储备金转给 to，不能超过资金池中剩余可借出的数量，只能管理者调用
+        #[ink(message)]
+        pub fn withdraw_reserves(&mut self, to: AccountId, amount: Balance) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyForOwner)
+            }
+            self.accrue_interest();
+            if amount > self.protocol_reserves {
+                return Err(Error::InsufficientReserves)
+            }
+            if amount > self.borrowings_balance {
+                return Err(Error::InsufficientLiquidity)
+            }
+            self.transfer_out(self.base_token_accountid, to, amount)?;
+            self.protocol_reserves -= amount;
+            self.borrowings_balance -= amount;
+
+            self.env().emit_event( ReservesWithdrawn{
+                to,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 返回清算人质押的基础代币数量
+        #[ink(message)]
+        pub fn keeper_stake_of(&self, keeper: AccountId) -> Balance {
+            self.keeper_stakes.get(&keeper).copied().unwrap_or(0)
+        }
+
+        // 返回所有清算人质押的总和
+        #[ink(message)]
+        pub fn total_keeper_stake(&self) -> Balance {
+            self.total_keeper_stake
+        }
+
+        // 返回用户是否是登记的清算人，即质押不为 0 且不少于 config 中的最少质押
+        #[ink(message)]
+        pub fn is_keeper(&self, account: AccountId) -> bool {
+            let stake = self.keeper_stake_of(account);
+            stake > 0 && stake >= self.config.min_keeper_stake
+        }
+
+        // 质押基础代币登记为清算人，可以多次调用增加质押
+        #[ink(message)]
+        pub fn stake_keeper(&mut self, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let caller = Self::env().caller();
+            self.transfer_in(self.base_token_accountid, caller, amount)?;
+            let total = self.keeper_stake_of(caller) + amount;
+            self.keeper_stakes.insert(caller, total);
+            self.total_keeper_stake += amount;
+
+            self.env().emit_event( KeeperStaked{
+                keeper : caller,
+                amount,
+                total,
+            });
+            Ok(())
+        }
+
+        // 取回清算人质押的基础代币，剩余质押少于最少质押后不能再清算
+        #[ink(message)]
+        pub fn unstake_keeper(&mut self, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let caller = Self::env().caller();
+            let stake = self.keeper_stake_of(caller);
+            if amount > stake {
+                return Err(Error::InsufficientStake)
+            }
+            self.transfer_out(self.base_token_accountid, caller, amount)?;
+            self.set_keeper_stake(caller, stake - amount);
+
+            self.env().emit_event( KeeperUnstaked{
+                keeper : caller,
+                amount,
+                total : stake - amount,
+            });
+            Ok(())
+        }
+
+        // 罚没恶意清算人的质押，比如反复提交失败的清算或者试图清算健康的仓位，
+        // 罚没的基础代币计入协议储备金，只能治理账号调用
+        #[ink(message)]
+        pub fn slash_keeper(&mut self, keeper: AccountId, amount: Balance) -> Result<()> {
+            self.ensure_governance()?;
+            let stake = self.keeper_stake_of(keeper);
+            if amount > stake {
+                return Err(Error::InsufficientStake)
+            }
+            self.set_keeper_stake(keeper, stake - amount);
+            self.protocol_reserves += amount;
+            self.borrowings_balance += amount;
+
+            self.env().emit_event( KeeperSlashed{
+                keeper,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 内部函数，把清算人的质押更新为 stake，并同步质押总和
+        fn set_keeper_stake(&mut self, keeper: AccountId, stake: Balance) {
+            let previous = self.keeper_stake_of(keeper);
+            if stake == 0 {
+                self.keeper_stakes.take(&keeper);
+            } else {
+                self.keeper_stakes.insert(keeper, stake);
+            }
+            self.total_keeper_stake = self.total_keeper_stake - previous + stake;
+        }
+
+        // 内部函数，设置了最少质押时只有登记的清算人可以清算
+        fn ensure_keeper(&self, account: AccountId) -> Result<()> {
+            if self.config.min_keeper_stake > 0 && !self.is_keeper(account) {
+                return Err(Error::NotKeeper)
+            }
+            Ok(())
+        }
+
+        // 内部函数，从协议储备金中给登记的清算人支付归还数量 × keeper_reward_bps 的奖励，
+        // 储备金或者资金池中剩余可借出的数量不足时只支付剩余的部分，
+        // 在清算的状态更新完成之后调用，转账失败时回滚整个清算
+        fn pay_keeper_reward(&mut self, keeper: AccountId, borrower: AccountId, repay: Balance) {
+            if !self.is_keeper(keeper) {
+                return
+            }
+            let reward = core::cmp::min(
+                repay * self.config.keeper_reward_bps as Balance / RATIO_DENOMINATOR,
+                core::cmp::min(self.protocol_reserves, self.borrowings_balance),
+            );
+            if reward == 0 {
+                return
+            }
+            self.protocol_reserves -= reward;
+            self.borrowings_balance -= reward;
+            if self.transfer_out(self.base_token_accountid, keeper, reward).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+
+            self.env().emit_event( KeeperRewarded{
+                keeper,
+                borrower,
+                amount : reward,
+            });
+        }
+
+        // 返回流动性挖矿的奖励代币
+        #[ink(message)]
+        pub fn reward_token(&self) -> Option<AccountId> {
+            self.reward_token
+        }
+
+        // 设置流动性挖矿的奖励代币，奖励代币需要由管理者通过 fund_rewards 转入，只能管理者调用
+        // 奖励代币不能是基础代币，否则领取奖励会转出存款人的资金；还有奖励余额时不能更换
+        #[ink(message)]
+        pub fn set_reward_token(&mut self, reward_token: Option<AccountId>) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyForOwner)
+            }
+            if reward_token == Some(self.base_token_accountid)
+                || (self.reward_balance > 0 && reward_token != self.reward_token)
+            {
+                return Err(Error::InvalidRewardToken)
+            }
+            self.reward_token = reward_token;
+            self.emit_params_updated(Param::Rewards, reward_token);
+            Ok(())
+        }
+
+        // 返回尚未被领取的奖励代币余额
+        #[ink(message)]
+        pub fn reward_balance(&self) -> Balance {
+            self.reward_balance
+        }
+
+        // 从调用者转入奖励代币作为可以领取的奖励，只能管理者调用
+        #[ink(message)]
+        pub fn fund_rewards(&mut self, amount: Balance) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyForOwner)
+            }
+            let reward_token = match self.reward_token {
+                Some(token) => token,
+                None => return Err(Error::RewardsDisabled),
+            };
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.transfer_in(reward_token, caller, amount)?;
+            self.reward_balance += amount;
+
+            self.env().emit_event( RewardsFunded{
+                owner: caller,
+                reward_token,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 返回每个区块分给存款人和借款人的奖励代币数量
+        #[ink(message)]
+        pub fn reward_speeds(&self) -> (Balance, Balance) {
+            (self.supply_reward_speed, self.borrow_reward_speed)
+        }
+
+        // 设置每个区块分给存款人和借款人的奖励代币数量，修改前先按原来的速度累积奖励，只能管理者调用
+        #[ink(message)]
+        pub fn set_reward_speeds(&mut self, supply_speed: Balance, borrow_speed: Balance) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyForOwner)
+            }
+            self.accrue_rewards();
+            self.supply_reward_speed = supply_speed;
+            self.borrow_reward_speed = borrow_speed;
+            self.emit_params_updated(Param::Rewards, None);
+            Ok(())
+        }
+
+        // 返回用户截至当前区块可以领取的奖励
+        // 存款奖励按份额占总份额的比例分配，借款奖励按浮动利率借款折算的本金占总本金的比例分配
+        #[ink(message)]
+        pub fn rewards_of(&self, account: AccountId) -> Balance {
+            let (supply_index, borrow_index) = self.current_reward_indexes();
+            let supply_delta = supply_index - self.supplier_reward_indexes.get(&account).copied().unwrap_or(0);
+            let borrow_delta = borrow_index - self.borrower_reward_indexes.get(&account).copied().unwrap_or(0);
+            self.accrued_rewards.get(&account).copied().unwrap_or(0)
+                + self.supply_shares_of(account) * supply_delta / INDEX_PRECISION
+                + self.scaled_borrowing_of(account) * borrow_delta / INDEX_PRECISION
+        }
+
+        // 领取调用者的奖励，返回领取的数量，奖励余额不足时只领取余额，剩下的部分在补充奖励后领取
+        #[ink(message)]
+        pub fn claim_rewards(&mut self) -> Result<Balance> {
+            let reward_token = match self.reward_token {
+                Some(token) => token,
+                None => return Err(Error::RewardsDisabled),
+            };
+            let caller = Self::env().caller();
+            self.distribute_supply_reward(caller);
+            self.settle_debt(caller);
+            let accrued = self.accrued_rewards.get(&caller).copied().unwrap_or(0);
+            if accrued == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            if self.reward_balance == 0 {
+                return Err(Error::InsufficientRewards)
+            }
+            let amount = core::cmp::min(accrued, self.reward_balance);
+            self.accrued_rewards.insert(caller, accrued - amount);
+            self.reward_balance -= amount;
+
+            self.env().emit_event( RewardsClaimed{
+                account: caller,
+                reward_token,
+                amount,
+            });
+            if self.transfer_out(reward_token, caller, amount).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+            Ok(amount)
+        }
+
+        // 内部函数，返回截至当前区块的存款和借款奖励指数，不修改存储
+        fn current_reward_indexes(&self) -> (u128, u128) {
+            let blocks = (self.env().block_number() - self.last_reward_block) as u128;
+            // 没有份额或借款时这段时间的奖励不分配
+            let supply_reward = (self.supply_reward_speed * blocks * INDEX_PRECISION)
+                .checked_div(self.total_supply_shares)
+                .unwrap_or(0);
+            let borrow_reward = (self.borrow_reward_speed * blocks * INDEX_PRECISION)
+                .checked_div(self.total_borrowings * INDEX_PRECISION / self.borrow_index)
+                .unwrap_or(0);
+            (self.supply_reward_index + supply_reward, self.borrow_reward_index + borrow_reward)
+        }
+
+        // 内部函数，把奖励指数累积到当前区块
+        fn accrue_rewards(&mut self) {
+            let block_number = self.env().block_number();
+            if block_number == self.last_reward_block {
+                return
+            }
+            let (supply_index, borrow_index) = self.current_reward_indexes();
+            self.supply_reward_index = supply_index;
+            self.borrow_reward_index = borrow_index;
+            self.last_reward_block = block_number;
+        }
+
+        // 内部函数，份额变化之前按用户当前的份额结算存款奖励
+        fn distribute_supply_reward(&mut self, account: AccountId) {
+            self.accrue_rewards();
+            let index = self.supplier_reward_indexes.get(&account).copied().unwrap_or(0);
+            let reward = self.supply_shares_of(account) * (self.supply_reward_index - index) / INDEX_PRECISION;
+            self.supplier_reward_indexes.insert(account, self.supply_reward_index);
+            self.add_accrued_reward(account, reward);
+        }
+
+        // 内部函数，借款变化之前按用户上次结算的借款折算的本金结算借款奖励
+        fn distribute_borrow_reward(&mut self, account: AccountId) {
+            self.accrue_rewards();
+            let index = self.borrower_reward_indexes.get(&account).copied().unwrap_or(0);
+            let reward = self.scaled_borrowing_of(account) * (self.borrow_reward_index - index) / INDEX_PRECISION;
+            self.borrower_reward_indexes.insert(account, self.borrow_reward_index);
+            self.add_accrued_reward(account, reward);
+        }
+
+        // 内部函数，返回用户的浮动利率借款按借款指数折算成的本金，即借款 / 用户的借款指数
+        // 利息累积时不变，与总借款 / 全局借款指数是同一个口径，借款奖励按它分配
+        fn scaled_borrowing_of(&self, account: AccountId) -> Balance {
+            let borrowed = self.borrowing_of(account);
+            if borrowed == 0 {
+                return 0
+            }
+            let account_index = self.account_borrow_index.get(&account).copied().unwrap_or(self.borrow_index);
+            borrowed * INDEX_PRECISION / account_index
+        }
+
+        // 内部函数，增加用户可以领取的奖励
+        fn add_accrued_reward(&mut self, account: AccountId, reward: Balance) {
+            if reward > 0 {
+                let accrued = self.accrued_rewards.get(&account).copied().unwrap_or(0);
+                self.accrued_rewards.insert(account, accrued + reward);
+            }
+        }
+
+        // 内部函数，先累积利息，再按借款指数的变化把用户的借款更新为包含利息的数量，并返回该数量
+        fn settle_debt(&mut self, account: AccountId) -> Balance {
+            self.accrue_interest();
+            self.distribute_borrow_reward(account);
+            let borrowed = self.borrowing_of(account);
+            if borrowed == 0 {
+                return 0
+            }
+            let index = self.borrow_index;
+            let account_index = self.account_borrow_index.get(&account).copied().unwrap_or(index);
+            let full_debt = borrowed * index / account_index;
+            let debt = self.apply_discount(account, borrowed, full_debt);
+            // 总借款和储备金都按全额利息累积，需要减去折扣掉的部分，储备金只按实际收取的利息计入
+            let discount = full_debt - debt;
+            self.total_borrowings = self.total_borrowings.saturating_sub(discount);
+            self.protocol_reserves = self.protocol_reserves
+                .saturating_sub(discount * self.config.reserve_factor_bps as Balance / RATIO_DENOMINATOR);
+            self.borrowings.insert(account, debt);
+            self.account_borrow_index.insert(account, index);
+            if debt > borrowed {
+                self.env().emit_event( DebtTransfer{
+                    from : None,
+                    to : Some(account),
+                    value : debt - borrowed,
+                });
+            }
+            debt
+        }
+
+        // 返回用户持有的债务代币数量，即按上次累积利息时的借款指数计算的借款数量，债务代币不能转让
+        #[ink(message)]
+        pub fn debt_balance_of(&self, account: AccountId) -> Balance {
+            let borrowed = self.borrowing_of(account);
+            if borrowed == 0 {
+                return 0
+            }
+            let index = self.borrow_index;
+            let account_index = self.account_borrow_index.get(&account).copied().unwrap_or(index);
+            self.apply_discount(account, borrowed, borrowed * index / account_index)
+        }
+
+        // 返回债务代币的总量，即包含利息的总借款
+        #[ink(message)]
+        pub fn debt_total_supply(&self) -> Balance {
+            self.total_borrowings
+        }
+
+        // 返回用户截至当前区块包含利息的借款数量，即现在调用 repay_all 需要归还的数量，不修改存储
+        // 固定期限借款需要归还的数量通过 fixed_debt_of 查询
+        #[ink(message)]
+        pub fn current_debt(&self, account: AccountId) -> Balance {
+            self.accrued_debt_of(account)
+        }
+
+        // 返回用户的健康系数，即所有质押按价格和清算线折算后的价值除以借款数量，放大了 INDEX_PRECISION 倍
+        // 小于 INDEX_PRECISION 时表示质押已经不足以覆盖借款，没有借款时返回 u128::MAX
+        #[ink(message)]
+        pub fn health_factor(&self, account: AccountId) -> Result<u128> {
+            let debt = self.debt_balance_of(account);
+            self.health_factor_for(account, debt)
+        }
+
+        // 内部函数，按指定的浮动利率借款数量加上其他借款计算用户的健康系数
+        fn health_factor_for(&self, account: AccountId, debt: Balance) -> Result<u128> {
+            let debt = debt + self.other_debt_of(account);
+            if debt == 0 {
+                return Ok(u128::MAX)
+            }
+            Ok(self.liquidation_power(account)? * INDEX_PRECISION / debt)
+        }
+
+        // 返回用户的借款仓位，借款和健康系数按截至当前区块的利息计算
+        #[ink(message)]
+        pub fn get_position(&self, account: AccountId) -> Result<Position> {
+            let collaterals = self.collateral_configs
+                .keys()
+                .map(|token| (*token, self.pledge_of(account, *token)))
+                .filter(|(_, pledged)| *pledged > 0)
+                .collect();
+            let variable_debt = self.accrued_debt_of(account);
+            let health_factor = self.health_factor_for(account, variable_debt)?;
+            let debt = variable_debt + self.other_debt_of(account);
+            let available_to_borrow = core::cmp::min(
+                self.borrowing_power(account)?.saturating_sub(debt),
+                self.borrowings_balance,
+            );
+            Ok(Position {
+                collaterals,
+                debt,
+                health_factor,
+                available_to_borrow,
+            })
+        }
+
+        // 返回从 offset 开始最多 limit 个有借款的用户
+        #[ink(message)]
+        pub fn borrowers(&self, offset: u32, limit: u32) -> Vec<AccountId> {
+            let end = core::cmp::min(offset.saturating_add(limit), self.borrower_list.len());
+            (offset..end)
+                .filter_map(|index| self.borrower_list.get(index).copied())
+                .collect()
+        }
+
+        // 返回借款用户的总数
+        #[ink(message)]
+        pub fn borrower_count(&self) -> u32 {
+            self.borrower_list.len()
+        }
+
+        // 在从 offset 开始的 limit 个借款用户中，返回可以被清算的用户及其健康系数
+        // 固定期限借款逾期的用户不论健康系数都会返回，读取不到价格的用户会被跳过
+        #[ink(message)]
+        pub fn unhealthy_positions(&self, offset: u32, limit: u32) -> Vec<(AccountId, u128)> {
+            let mut positions = Vec::new();
+            for account in self.borrowers(offset, limit) {
+                let debt = self.accrued_debt_of(account);
+                if let Ok(health_factor) = self.health_factor_for(account, debt) {
+                    if health_factor < INDEX_PRECISION || self.is_fixed_loan_overdue(account) {
+                        positions.push((account, health_factor));
+                    }
+                }
+            }
+            positions
+        }
+
+        // 内部函数，用户产生借款时加入借款用户列表
+        fn track_borrower(&mut self, account: AccountId) {
+            if self.borrower_positions.contains_key(&account) {
+                return
+            }
+            self.borrower_positions.insert(account, self.borrower_list.len());
+            self.borrower_list.push(account);
+        }
+
+        // 内部函数，用户的所有借款都还清后移出借款用户列表，
+        // 用列表的最后一个用户填补空位
+        fn untrack_borrower(&mut self, account: AccountId) {
+            if self.borrowings.contains_key(&account)
+                || self.stable_debts.contains_key(&account)
+                || self.fixed_loans.contains_key(&account)
+            {
+                return
+            }
+            if let Some(bad_debt) = self.bad_debts.take(&account) {
+                self.total_bad_debt = self.total_bad_debt.saturating_sub(bad_debt);
+            }
+            self.reduce_isolated_debt(account, Balance::MAX);
+            if let Some(index) = self.borrower_positions.take(&account) {
+                self.borrower_list.swap_remove_drop(index);
+                if let Some(moved) = self.borrower_list.get(index).copied() {
+                    self.borrower_positions.insert(moved, index);
+                }
+            }
+        }
+
+        // 返回被授权转让 owner 的借款仓位的账号
+        #[ink(message)]
+        pub fn position_approved(&self, owner: AccountId) -> Option<AccountId> {
+            self.position_approvals.get(&owner).copied()
+        }
+
+        // 授权 approved 转让调用者的借款仓位，None 表示取消授权
+        #[ink(message)]
+        pub fn approve_position(&mut self, approved: Option<AccountId>) -> Result<()> {
+            let caller = Self::env().caller();
+            match approved {
+                Some(approved) => { self.position_approvals.insert(caller, approved); }
+                None => { self.position_approvals.take(&caller); }
+            }
+            self.env().emit_event( PositionApproval{
+                owner : caller,
+                approved,
+            });
+            Ok(())
+        }
+
+        // 返回 to 同意接收其借款仓位的用户
+        #[ink(message)]
+        pub fn position_accepted(&self, to: AccountId) -> Option<AccountId> {
+            self.position_acceptances.get(&to).copied()
+        }
+
+        // 同意接收 from 的借款仓位，None 表示取消同意
+        #[ink(message)]
+        pub fn accept_position(&mut self, from: Option<AccountId>) -> Result<()> {
+            let caller = Self::env().caller();
+            match from {
+                Some(from) => { self.position_acceptances.insert(caller, from); }
+                None => { self.position_acceptances.take(&caller); }
+            }
+            self.env().emit_event( PositionAcceptance{
+                to: caller,
+                from,
+            });
+            Ok(())
+        }
+
+        // 把 from 的借款仓位转让给 to，调用者必须是 from 或者被 from 授权的账号
+        // to 必须是调用者本人或者已经通过 accept_position 同意接收 from 的仓位，避免把借款和清算风险强加给别人
+        // 仓位的借款、全部质押和被委托人用 from 的信用借出的记录一起转给 to，之后只有 to 可以还款、借款和取回质押
+        // to 不能已经有借款或质押，仓位在拍卖中时不能转让，from 授予的信用额度不随仓位转让
+        #[ink(message)]
+        pub fn transfer_position(&mut self, from: AccountId, to: AccountId) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != from && self.position_approvals.get(&from) != Some(&caller) {
+                return Err(Error::NotPositionOwner)
+            }
+            if !self.borrower_positions.contains_key(&from) && !self.has_collateral(from) {
+                return Err(Error::NoPosition)
+            }
+            if to == from {
+                return Ok(())
+            }
+            if caller != to && self.position_acceptances.get(&to) != Some(&from) {
+                return Err(Error::PositionNotAccepted)
+            }
+            if self.borrowings.contains_key(&to)
+                || self.stable_debts.contains_key(&to)
+                || self.fixed_loans.contains_key(&to)
+                || self.has_collateral(to)
+            {
+                return Err(Error::PositionExists)
+            }
+            let tokens: Vec<AccountId> = self.collateral_configs.keys().copied().collect();
+            if tokens.iter().any(|token| self.auctions.contains_key(&(from, *token))) {
+                return Err(Error::AuctionExists)
+            }
+            let borrowed = self.settle_debt(from);
+            self.ensure_tier_limit(to, 0, borrowed + self.other_debt_of(from))?;
+            self.distribute_borrow_reward(to);
+
+            for token in tokens {
+                if let Some(pledged) = self.pledges.take(&(from, token)) {
+                    self.pledges.insert((to, token), pledged);
+                }
+            }
+            if let Some(borrowed) = self.borrowings.take(&from) {
+                self.borrowings.insert(to, borrowed);
+            }
+            if let Some(index) = self.account_borrow_index.take(&from) {
+                self.account_borrow_index.insert(to, index);
+            }
+            if let Some(debt) = self.stable_debts.take(&from) {
+                self.stable_debts.insert(to, debt);
+            }
+            if let Some(loan) = self.fixed_loans.take(&from) {
+                self.fixed_loans.insert(to, loan);
+            }
+            if let Some(block) = self.at_risk_since.take(&from) {
+                self.at_risk_since.insert(to, block);
+            }
+            if let Some(bad_debt) = self.bad_debts.take(&from) {
+                self.bad_debts.insert(to, bad_debt);
+            }
+            if let Some(isolated) = self.isolated_debts.take(&from) {
+                self.isolated_debts.insert(to, isolated);
+            }
+            let delegated: Vec<(AccountId, AccountId)> = self.delegated_debts
+                .keys()
+                .filter(|(delegator, _)| *delegator == from)
+                .copied()
+                .collect();
+            for (delegator, delegatee) in delegated {
+                if let Some(debt) = self.delegated_debts.take(&(delegator, delegatee)) {
+                    self.delegated_debts.insert((to, delegatee), debt);
+                }
+            }
+            if let Some(index) = self.borrower_positions.take(&from) {
+                self.borrower_positions.insert(to, index);
+                if let Some(account) = self.borrower_list.get_mut(index) {
+                    *account = to;
+                }
+            }
+            self.position_approvals.take(&from);
+            self.position_acceptances.take(&to);
+
+            if borrowed > 0 {
+                self.env().emit_event( DebtTransfer{
+                    from : Some(from),
+                    to : Some(to),
+                    value : borrowed,
+                });
+            }
+            self.env().emit_event( PositionTransfer{
+                from,
+                to,
+            });
+            Ok(())
+        }
+
+        // 内部函数，返回截至当前区块的借款指数，不修改存储
+        fn current_borrow_index(&self) -> u128 {
+            let blocks = self.env().block_number() - self.last_accrual_block;
+            let interest_factor = self.borrow_rate() * blocks as u128;
+            self.borrow_index + self.borrow_index * interest_factor / INDEX_PRECISION
+        }
+
+        // 内部函数，返回用户截至当前区块包含利息的借款数量，不修改存储
+        fn accrued_debt_of(&self, account: AccountId) -> Balance {
+            let borrowed = self.borrowing_of(account);
+            if borrowed == 0 {
+                return 0
+            }
+            let index = self.current_borrow_index();
+            let account_index = self.account_borrow_index.get(&account).copied().unwrap_or(index);
+            self.apply_discount(account, borrowed, borrowed * index / account_index)
+        }
+
+        // 内部函数，按用户借款等级的利率折扣减少从 borrowed 增长到 debt 的利息
+        fn apply_discount(&self, account: AccountId, borrowed: Balance, debt: Balance) -> Balance {
+            let discount_bps = self.tier_config_of(account)
+                .map(|config| config.rate_discount_bps)
+                .unwrap_or(0);
+            debt - (debt - borrowed) * discount_bps as Balance / RATIO_DENOMINATOR
+        }
+
+        // 返回借款等级的参数
+        #[ink(message)]
+        pub fn tier_config(&self, tier: Tier) -> Option<TierConfig> {
+            self.tier_configs.get(&tier).copied()
+        }
+
+        // 设置借款等级的借款上限和利率折扣，只能治理账号调用
+        #[ink(message)]
+        pub fn set_tier_config(&mut self, tier: Tier, config: TierConfig) -> Result<()> {
+            self.ensure_governance()?;
+            if config.rate_discount_bps as Balance > RATIO_DENOMINATOR {
+                return Err(Error::InvalidRatio)
+            }
+            self.tier_configs.insert(tier, config);
+            self.emit_params_updated(Param::Tier, None);
+            Ok(())
+        }
+
+        // 返回用户的借款等级
+        #[ink(message)]
+        pub fn tier_of(&self, account: AccountId) -> Option<Tier> {
+            self.account_tiers.get(&account).copied()
+        }
+
+        // 设置用户的借款等级，None 表示取消等级，已有借款的利息会先按原来的等级结算，只能管理者调用
+        #[ink(message)]
+        pub fn set_tier(&mut self, account: AccountId, tier: Option<Tier>) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyForOwner)
+            }
+            self.settle_debt(account);
+            match tier {
+                Some(tier) => self.account_tiers.insert(account, tier),
+                None => self.account_tiers.take(&account),
+            };
+            self.emit_params_updated(Param::Tier, None);
+            Ok(())
+        }
+
+        // 内部函数，返回用户所在借款等级的参数
+        fn tier_config_of(&self, account: AccountId) -> Option<TierConfig> {
+            self.account_tiers
+                .get(&account)
+                .and_then(|tier| self.tier_configs.get(tier))
+                .copied()
+        }
+
+        // 内部函数，确认用户再借出 amount 后不超过借款等级的借款上限
+        fn ensure_tier_limit(&self, account: AccountId, borrowed: Balance, amount: Balance) -> Result<()> {
+            if let Some(config) = self.tier_config_of(account) {
+                if borrowed + self.other_debt_of(account) + amount > config.max_borrow {
+                    return Err(Error::TierLimitExceeded)
+                }
+            }
+            Ok(())
+        }
+
+        // 内部函数，返回用户所有质押最多可以借出的数量之和
+        fn borrowing_power(&self, account: AccountId) -> Result<Balance> {
+            let mut power = 0;
+            for token in self.collateral_configs.keys() {
+                let pledged = self.pledge_of(account, *token);
+                if pledged > 0 {
+                    power += self.collateral_limit(*token, pledged)?;
+                }
+            }
+            Ok(power)
+        }
+
+        // 内部函数，返回用户所有质押按清算线折算后的价值之和
+        fn liquidation_power(&self, account: AccountId) -> Result<Balance> {
+            let mut power = 0;
+            for (token, config) in self.collateral_configs.iter() {
+                let pledged = self.pledge_of(account, *token);
+                if pledged > 0 {
+                    power += self.collateral_value(*token, pledged)?
+                        * config.liquidation_threshold_bps as Balance / RATIO_DENOMINATOR;
+                }
+            }
+            Ok(power)
+        }
+
+        // 内部函数，返回质押 pledged 数量的指定代币最多可以借出的数量，即质押代币按价格折算成基础代币后乘以借款比例
+        fn collateral_limit(&self, collateral_token: AccountId, pledged: Balance) -> Result<Balance> {
+            let factor = self.collateral_configs
+                .get(&collateral_token)
+                .map(|config| config.collateral_factor_bps)
+                .unwrap_or(0);
+            Ok(self.collateral_value(collateral_token, pledged)? * factor as Balance / RATIO_DENOMINATOR)
+        }
+
+        // 内部函数，返回 pledged 数量的指定代币按价格折算成的基础代币数量
+        fn collateral_value(&self, collateral_token: AccountId, pledged: Balance) -> Result<Balance> {
+            Ok(pledged * self.price_of(collateral_token)? / PRICE_PRECISION)
+        }
+
+        // 内部函数，跨合约调用代币的 transfer，把本合约的代币转给 to
+        // 代币返回 Err 或者返回值无法解码时都视为转账失败
+        #[cfg(not(test))]
+        fn transfer_out(&self, token: AccountId, to: AccountId, amount: Balance) -> Result<()> {
+            let result = build_call::<Environment>()
+                .callee(token)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(TRANSFER_SELECTOR))
+                        .push_arg(to)
+                        .push_arg(amount)
+                )
+                .returns::<ReturnType<core::result::Result<(), u8>>>()
+                .fire();
+            match result {
+                Ok(Ok(())) => Ok(()),
+                _ => Err(Error::TokenTransferFailed),
+            }
+        }
+
+        // 内部函数，跨合约调用代币的 transfer_from，把 from 授权给本合约的代币转入本合约
+        #[cfg(not(test))]
+        fn transfer_in(&self, token: AccountId, from: AccountId, amount: Balance) -> Result<()> {
+            let self_accountid = Self::env().account_id();
+            let result = build_call::<Environment>()
+                .callee(token)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(TRANSFER_FROM_SELECTOR))
+                        .push_arg(from)
+                        .push_arg(self_accountid)
+                        .push_arg(amount)
+                )
+                .returns::<ReturnType<core::result::Result<(), u8>>>()
+                .fire();
+            match result {
+                Ok(Ok(())) => Ok(()),
+                _ => Err(Error::TokenTransferFailed),
+            }
+        }
+
+        // 链下测试环境不支持跨合约调用，测试时代币转账改为操作 tests 模块中模拟的 ERC20 账本
+        #[cfg(test)]
+        fn transfer_out(&self, token: AccountId, to: AccountId, amount: Balance) -> Result<()> {
+            let self_accountid = Self::env().account_id();
+            if tests::mock_erc20::transfer(token, self_accountid, to, amount) {
+                Ok(())
+            } else {
+                Err(Error::TokenTransferFailed)
+            }
+        }
+
+        #[cfg(test)]
+        fn transfer_in(&self, token: AccountId, from: AccountId, amount: Balance) -> Result<()> {
+            let self_accountid = Self::env().account_id();
+            if tests::mock_erc20::transfer_from(token, self_accountid, from, self_accountid, amount) {
+                Ok(())
+            } else {
+                Err(Error::TokenTransferFailed)
+            }
+        }
+
+        // 内部函数，通过链扩展从运行时读取质押币种的价格，没有设置编号或者价格过期都视为无法获取价格
+        #[cfg(not(test))]
+        fn fetch_price(&self, collateral_token: AccountId) -> Result<u128> {
+            let asset = *self.price_assets.get(&collateral_token).ok_or(Error::PriceUnavailable)?;
+            match self.env().extension().fetch_price(asset) {
+                Ok((price, updated_at)) if updated_at + self.config.max_price_age >= self.env().block_timestamp() => Ok(price),
+                _ => Err(Error::PriceUnavailable),
+            }
+        }
+
+        // 链下测试环境不能注册自定义的链扩展，测试时价格改为读取 tests 模块中模拟的价格
+        #[cfg(test)]
+        fn fetch_price(&self, collateral_token: AccountId) -> Result<u128> {
+            tests::mock_price::price_of(collateral_token).ok_or(Error::PriceUnavailable)
+        }
+
+        // 内部函数，读取质押币种的价格，设置了预言机合约时跨合约调用预言机，否则通过链扩展读取
+        fn price_of(&self, collateral_token: AccountId) -> Result<u128> {
+            let oracle = match self.price_oracle {
+                Some(oracle) => oracle,
+                None => return self.fetch_price(collateral_token),
+            };
+            let result = build_call::<Environment>()
+                .callee(oracle)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(PRICE_SELECTOR))
+                        .push_arg(collateral_token)
+                )
+                .returns::<ReturnType<(u128, Timestamp)>>()
+                .fire();
+            match result {
+                Ok((price, updated_at)) if updated_at + self.config.max_price_age >= self.env().block_timestamp() => Ok(price),
+                _ => Err(Error::PriceUnavailable),
+            }
+        }
+
+        // 返回价格预言机合约，价格的最长有效时间在 config 中
+        #[ink(message)]
+        pub fn price_oracle(&self) -> Option<AccountId> {
+            self.price_oracle
+        }
+
+        // 返回质押币种在运行时预言机中的编号
+        #[ink(message)]
+        pub fn price_asset(&self, collateral_token: AccountId) -> Option<u32> {
+            self.price_assets.get(&collateral_token).copied()
+        }
+
+        // 设置通过链扩展读取价格时质押币种在运行时预言机中的编号，None 表示删除，只能治理账号调用
+        #[ink(message)]
+        pub fn set_price_asset(&mut self, collateral_token: AccountId, asset: Option<u32>) -> Result<()> {
+            self.ensure_governance()?;
+            match asset {
+                Some(asset) => { self.price_assets.insert(collateral_token, asset); }
+                None => { self.price_assets.take(&collateral_token); }
+            }
+            self.emit_params_updated(Param::PriceOracle, Some(collateral_token));
+            Ok(())
+        }
+
+        // 设置价格预言机合约，None 表示改回通过链扩展读取价格，只能治理账号调用
+        #[ink(message)]
+        pub fn set_price_oracle(&mut self, oracle: Option<AccountId>) -> Result<()> {
+            self.ensure_governance()?;
+            self.price_oracle = oracle;
+            self.emit_params_updated(Param::PriceOracle, None);
+            Ok(())
+        }
+
+        // 借出基础代币，collateral_token 为本次借款主要使用的质押，借款总额不能超过所有质押最多可以借出的数量之和
+        #[ink(message)]
+        pub fn borrow(&mut self, collateral_token: AccountId, amount: Balance) -> Result<()> {
+            let caller = Self::env().caller();
+            self.borrow_for(caller, caller, collateral_token, amount)
+        }
+
+        // 内部函数，用 borrower 的质押借出基础代币并转给 recipient，债务记在 borrower 名下
+        fn borrow_for(&mut self, borrower: AccountId, recipient: AccountId, collateral_token: AccountId, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.ensure_not_paused(PauseAction::Borrow)?;
+            if !self.collateral_configs.contains_key(&collateral_token) {
+                return Err(Error::UnsupportedCollateral)
+            }
+            let borrowed = self.settle_debt(borrower);
+            if borrowed + self.other_debt_of(borrower) + amount > self.borrowing_power(borrower)? {
+                return Err(Error::InsufficientCollateral)
+            }
+            self.ensure_tier_limit(borrower, borrowed, amount)?;
+            self.ensure_isolation_ceiling(borrower, amount)?;
+            if amount > self.borrowings_balance {
+                return Err(Error::InsufficientLiquidity)
+            }
+            let total_borrowed = self.total_debt();
+            if exceeds(self.config.borrow_cap, total_borrowed + amount) {
+                return Err(Error::CapReached)
+            }
+            self.transfer_out(self.base_token_accountid, recipient, amount)?;
+
+            self.borrowings.insert(borrower, borrowed + amount);
+            self.account_borrow_index.insert(borrower, self.borrow_index);
+            self.track_borrower(borrower);
+            self.add_isolated_debt(borrower, amount);
+            self.total_borrowings += amount;
+            self.borrowings_balance -= amount;
+
+            self.env().emit_event( DebtTransfer{
+                from : None,
+                to : Some(borrower),
+                value : amount,
+            });
+            self.env().emit_event( Borrowed{
+                account : borrower,
+                collateral_token,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 归还借款和累积的利息，超过借款数量的部分不会被转入
+        #[ink(message)]
+        pub fn repay(&mut self, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let caller = Self::env().caller();
+            self.repay_from(caller, caller, amount)?;
+            Ok(())
+        }
+
+        // 归还全部借款
+        #[ink(message)]
+        pub fn repay_all(&mut self) -> Result<()> {
+            let caller = Self::env().caller();
+            let borrowed = self.settle_debt(caller);
+            self.repay_from(caller, caller, borrowed)?;
+            Ok(())
+        }
+
+        // 按指定的利率模式借出基础代币，浮动利率与 borrow 相同，稳定利率按当前的浮动利率加上 config 中的溢价确定
+        #[ink(message)]
+        pub fn borrow_with_mode(&mut self, collateral_token: AccountId, amount: Balance, mode: RateMode) -> Result<()> {
+            let caller = Self::env().caller();
+            match mode {
+                RateMode::Variable => self.borrow_for(caller, caller, collateral_token, amount),
+                RateMode::Stable => self.borrow_stable(caller, collateral_token, amount),
+            }
+        }
+
+        // 内部函数，借出稳定利率借款，已有稳定利率借款时先结算利息，再按新的利率计算全部稳定利率借款
+        fn borrow_stable(&mut self, caller: AccountId, collateral_token: AccountId, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.ensure_not_paused(PauseAction::Borrow)?;
+            if !self.collateral_configs.contains_key(&collateral_token) {
+                return Err(Error::UnsupportedCollateral)
+            }
+            let borrowed = self.settle_debt(caller);
+            if borrowed + self.other_debt_of(caller) + amount > self.borrowing_power(caller)? {
+                return Err(Error::InsufficientCollateral)
+            }
+            self.ensure_tier_limit(caller, borrowed, amount)?;
+            self.ensure_isolation_ceiling(caller, amount)?;
+            if amount > self.borrowings_balance {
+                return Err(Error::InsufficientLiquidity)
+            }
+            if exceeds(self.config.borrow_cap, self.total_debt() + amount) {
+                return Err(Error::CapReached)
+            }
+            self.transfer_out(self.base_token_accountid, caller, amount)?;
+
+            let stable_debt = self.settle_stable_debt(caller);
+            let rate = self.stable_rate();
+            self.stable_debts.insert(caller, StableDebt {
+                amount: stable_debt + amount,
+                rate,
+                last_update_block: self.env().block_number(),
+            });
+            self.total_stable_debt += amount;
+            self.borrowings_balance -= amount;
+            self.track_borrower(caller);
+            self.add_isolated_debt(caller, amount);
+
+            self.env().emit_event( StableBorrowed{
+                account : caller,
+                collateral_token,
+                amount,
+                rate,
+            });
+            Ok(())
+        }
+
+        // 返回新的稳定利率借款现在可以获得的利率
+        #[ink(message)]
+        pub fn stable_rate(&self) -> u128 {
+            self.borrow_rate() + self.config.stable_rate_premium
+        }
+
+        // 返回用户的稳定利率借款
+        #[ink(message)]
+        pub fn stable_debt(&self, account: AccountId) -> Option<StableDebt> {
+            self.stable_debts.get(&account).copied()
+        }
+
+        // 返回用户截至当前区块包含利息的稳定利率借款数量
+        #[ink(message)]
+        pub fn stable_debt_of(&self, account: AccountId) -> Balance {
+            match self.stable_debts.get(&account) {
+                Some(debt) => {
+                    let blocks = self.env().block_number() - debt.last_update_block;
+                    debt.amount + debt.amount * debt.rate * blocks as u128 / INDEX_PRECISION
+                }
+                None => 0,
+            }
+        }
+
+        // 内部函数，把稳定利率借款的利息并入借款数量，返回结算后的数量
+        fn settle_stable_debt(&mut self, account: AccountId) -> Balance {
+            let mut debt = match self.stable_debts.get(&account) {
+                Some(debt) => *debt,
+                None => return 0,
+            };
+            let amount = self.stable_debt_of(account);
+            self.total_stable_debt += amount - debt.amount;
+            debt.amount = amount;
+            debt.last_update_block = self.env().block_number();
+            self.stable_debts.insert(account, debt);
+            amount
+        }
+
+        // 内部函数，已经收到 amount 数量的基础代币后，从借款人结算后的稳定利率借款 debt 中扣除
+        fn reduce_stable_debt(&mut self, borrower: AccountId, debt: Balance, amount: Balance) {
+            self.reduce_isolated_debt(borrower, amount);
+            if debt == amount {
+                self.stable_debts.take(&borrower);
+                self.untrack_borrower(borrower);
+            } else if let Some(stable) = self.stable_debts.get_mut(&borrower) {
+                stable.amount = debt - amount;
+            }
+            self.total_stable_debt = self.total_stable_debt.saturating_sub(amount);
+            self.borrowings_balance += amount;
+        }
+
+        // 内部函数，已经收到 amount 数量的基础代币后，先归还浮动利率借款，再归还稳定利率借款
+        fn reduce_variable_and_stable_debt(&mut self, borrower: AccountId, borrowed: Balance, stable_debt: Balance, amount: Balance) {
+            let variable_repay = core::cmp::min(amount, borrowed);
+            if variable_repay > 0 {
+                self.reduce_debt(borrower, borrowed, variable_repay);
+            }
+            if amount > variable_repay {
+                self.reduce_stable_debt(borrower, stable_debt, amount - variable_repay);
+            }
+        }
+
+        // 归还稳定利率借款和累积的利息，超过借款数量的部分不会被转入
+        #[ink(message)]
+        pub fn repay_stable(&mut self, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let caller = Self::env().caller();
+            let debt = self.settle_stable_debt(caller);
+            if debt == 0 {
+                return Err(Error::NoDebt)
+            }
+            let amount = core::cmp::min(amount, debt);
+            self.transfer_in(self.base_token_accountid, caller, amount)?;
+            self.reduce_stable_debt(caller, debt, amount);
+            self.env().emit_event( Repaid{
+                account : caller,
+                amount,
+            });
+            self.clear_risk_if_healthy(caller);
+            Ok(())
+        }
+
+        // 切换借款的利率模式，有稳定利率借款时全部转为浮动利率，否则把全部浮动利率借款转为按当前利率计算的稳定利率借款
+        #[ink(message)]
+        pub fn swap_rate_mode(&mut self) -> Result<()> {
+            let caller = Self::env().caller();
+            let borrowed = self.settle_debt(caller);
+            let stable_debt = self.settle_stable_debt(caller);
+            let (to_mode, amount) = if stable_debt > 0 {
+                self.stable_debts.take(&caller);
+                self.total_stable_debt = self.total_stable_debt.saturating_sub(stable_debt);
+                self.borrowings.insert(caller, borrowed + stable_debt);
+                self.account_borrow_index.insert(caller, self.borrow_index);
+                self.total_borrowings += stable_debt;
+                self.env().emit_event( DebtTransfer{
+                    from : None,
+                    to : Some(caller),
+                    value : stable_debt,
+                });
+                (RateMode::Variable, stable_debt)
+            } else if borrowed > 0 {
+                self.borrowings.take(&caller);
+                self.account_borrow_index.take(&caller);
+                self.total_borrowings = self.total_borrowings.saturating_sub(borrowed);
+                self.env().emit_event( DebtTransfer{
+                    from : Some(caller),
+                    to : None,
+                    value : borrowed,
+                });
+                self.stable_debts.insert(caller, StableDebt {
+                    amount: borrowed,
+                    rate: self.stable_rate(),
+                    last_update_block: self.env().block_number(),
+                });
+                self.total_stable_debt += borrowed;
+                (RateMode::Stable, borrowed)
+            } else {
+                return Err(Error::NoDebt)
+            };
+            self.env().emit_event( RateModeSwapped{
+                account : caller,
+                to_mode,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 一次还清调用者全部的浮动利率、稳定利率和固定期限借款，并取回在所有支持的质押币种上的质押
+        // 先更新全部状态，再一次转入全部借款、逐个转出质押，任何一笔转账失败都回滚整个调用
+        #[ink(message)]
+        pub fn close_position(&mut self) -> Result<()> {
+            self.ensure_not_paused(PauseAction::Withdraw)?;
+            let caller = Self::env().caller();
+            let borrowed = self.settle_debt(caller);
+            if borrowed > 0 {
+                self.reduce_debt(caller, borrowed, borrowed);
+                self.env().emit_event( Repaid{
+                    account: caller,
+                    amount: borrowed,
+                });
+            }
+            let stable_debt = self.settle_stable_debt(caller);
+            if stable_debt > 0 {
+                self.reduce_stable_debt(caller, stable_debt, stable_debt);
+                self.env().emit_event( Repaid{
+                    account: caller,
+                    amount: stable_debt,
+                });
+            }
+            let fixed_debt = self.fixed_debt_of(caller);
+            if fixed_debt > 0 {
+                self.reduce_fixed_debt(caller, fixed_debt, fixed_debt);
+                self.env().emit_event( FixedRepaid{
+                    account: caller,
+                    amount: fixed_debt,
+                    remaining: 0,
+                });
+            }
+            self.clear_risk_if_healthy(caller);
+            let pledges: Vec<(AccountId, Balance)> = self.collateral_configs
+                .keys()
+                .map(|token| (*token, self.pledge_of(caller, *token)))
+                .filter(|(_, pledged)| *pledged > 0)
+                .collect();
+            for (token, pledged) in pledges.iter() {
+                self.reduce_pledge(caller, *token, *pledged);
+                self.env().emit_event( CollateralWithdrawn{
+                    account: caller,
+                    token: *token,
+                    amount: *pledged,
+                });
+            }
+
+            let debt = borrowed + stable_debt + fixed_debt;
+            if debt > 0 && self.transfer_in(self.base_token_accountid, caller, debt).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+            for (token, pledged) in pledges {
+                if self.transfer_out(token, caller, pledged).is_err() {
+                    Self::revert(Error::TokenTransferFailed)
+                }
+            }
+            Ok(())
+        }
+
+        // 用调用者最多 amount 数量的质押按预言机价格归还借款，依次归还浮动利率、稳定利率和固定期限借款，
+        // 质押价值超过借款时只使用刚好还清借款的数量，返回实际使用的质押数量
+        // 使用的质押由协议持有，管理者通过 withdraw_protocol_collateral 取出卖出后用 replenish 补回资金池
+        #[ink(message)]
+        pub fn repay_with_collateral(&mut self, collateral_token: AccountId, amount: Balance) -> Result<Balance> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            if !self.collateral_configs.contains_key(&collateral_token) {
+                return Err(Error::UnsupportedCollateral)
+            }
+            let caller = Self::env().caller();
+            if amount > self.pledge_of(caller, collateral_token) {
+                return Err(Error::InsufficientPledge)
+            }
+            let borrowed = self.settle_debt(caller);
+            let stable_debt = self.settle_stable_debt(caller);
+            let fixed_debt = self.fixed_debt_of(caller);
+            let debt = borrowed + stable_debt + fixed_debt;
+            if debt == 0 {
+                return Err(Error::NoDebt)
+            }
+
+            let price = self.price_of(collateral_token)?;
+            let mut used = amount;
+            let mut repay = used * price / PRICE_PRECISION;
+            if repay > debt {
+                used = (debt * PRICE_PRECISION).div_ceil(price);
+                repay = debt;
+            }
+            if repay == 0 {
+                return Err(Error::InsufficientPledge)
+            }
+
+            let fixed_repay = repay.saturating_sub(borrowed + stable_debt);
+            self.reduce_variable_and_stable_debt(caller, borrowed, stable_debt, repay - fixed_repay);
+            if fixed_repay > 0 {
+                self.reduce_fixed_debt(caller, fixed_debt, fixed_repay);
+            }
+            // 归还借款的内部函数会把归还数量计入资金池，这里没有转入基础代币，需要扣回
+            self.borrowings_balance -= repay;
+            self.pending_replenishment += repay;
+            self.reduce_pledge(caller, collateral_token, used);
+            let held = self.protocol_collateral_of(collateral_token);
+            self.protocol_collateral.insert(collateral_token, held + used);
+
+            self.env().emit_event( RepaidWithCollateral{
+                account : caller,
+                collateral_token,
+                collateral_amount : used,
+                repaid : repay,
+            });
+            self.clear_risk_if_healthy(caller);
+            self.record_bad_debt(caller);
+            Ok(used)
+        }
+
+        // 返回协议持有的指定质押代币数量
+        #[ink(message)]
+        pub fn protocol_collateral_of(&self, token: AccountId) -> Balance {
+            self.protocol_collateral.get(&token).copied().unwrap_or(0)
+        }
+
+        // 取出协议持有的质押代币转给 to，只能管理者调用
+        #[ink(message)]
+        pub fn withdraw_protocol_collateral(&mut self, token: AccountId, to: AccountId, amount: Balance) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyForOwner)
+            }
+            let held = self.protocol_collateral_of(token);
+            if amount > held {
+                return Err(Error::InsufficientReserves)
+            }
+            self.transfer_out(token, to, amount)?;
+            self.protocol_collateral.insert(token, held - amount);
+
+            self.env().emit_event( ProtocolCollateralWithdrawn{
+                token,
+                to,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 返回用质押归还、尚未补回资金池的借款数量
+        #[ink(message)]
+        pub fn pending_replenishment(&self) -> Balance {
+            self.pending_replenishment
+        }
+
+        // 转入基础代币补回用质押归还的借款，计入可借出数量而不增加份额，超过待补回数量的部分归所有存款人，只能管理者调用
+        #[ink(message)]
+        pub fn replenish(&mut self, amount: Balance) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyForOwner)
+            }
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.transfer_in(self.base_token_accountid, caller, amount)?;
+            self.borrowings_balance += amount;
+            self.pending_replenishment = self.pending_replenishment.saturating_sub(amount);
+
+            self.env().emit_event( Replenished{
+                owner : caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 替其他借款人归还借款，基础代币从调用者转入，超过借款数量的部分不会被转入
+        #[ink(message)]
+        pub fn repay_on_behalf(&mut self, borrower: AccountId, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let caller = Self::env().caller();
+            let repaid = self.repay_from(caller, borrower, amount)?;
+            self.env().emit_event( RepaidOnBehalf{
+                payer : caller,
+                borrower,
+                amount : repaid,
+            });
+            Ok(())
+        }
+
+        // 内部函数，通过 transfer_from 从 payer 转入基础代币归还 borrower 的借款，
+        // 归还数量最多为当前的借款数量，返回实际归还的数量
+        fn repay_from(&mut self, payer: AccountId, borrower: AccountId, amount: Balance) -> Result<Balance> {
+            let borrowed = self.settle_debt(borrower);
+            if borrowed == 0 {
+                return Err(Error::NoDebt)
+            }
+            let amount = core::cmp::min(amount, borrowed);
+            self.transfer_in(self.base_token_accountid, payer, amount)?;
+
+            self.reduce_debt(borrower, borrowed, amount);
+            self.env().emit_event( Repaid{
+                account : borrower,
+                amount,
+            });
+            self.clear_risk_if_healthy(borrower);
+            Ok(amount)
+        }
+
+        // 设置被委托人可以用自己的质押借出的数量，被委托人借出的债务记在自己名下
+        #[ink(message)]
+        pub fn approve_credit(&mut self, delegatee: AccountId, amount: Balance) -> Result<()> {
+            let delegator = Self::env().caller();
+            self.credit_allowances.insert((delegator, delegatee), amount);
+            self.env().emit_event( CreditApproval{
+                delegator,
+                delegatee,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 返回 delegator 给 delegatee 剩余的信用额度
+        #[ink(message)]
+        pub fn credit_allowance(&self, delegator: AccountId, delegatee: AccountId) -> Balance {
+            self.credit_allowances.get(&(delegator, delegatee)).copied().unwrap_or(0)
+        }
+
+        // 返回 delegatee 用 delegator 的质押借出且尚未归还的本金
+        #[ink(message)]
+        pub fn delegated_debt_of(&self, delegator: AccountId, delegatee: AccountId) -> Balance {
+            self.delegated_debts.get(&(delegator, delegatee)).copied().unwrap_or(0)
+        }
+
+        // 使用委托人授予的信用额度，用委托人的质押借出基础代币，借款转给调用者，债务记在委托人名下
+        #[ink(message)]
+        pub fn borrow_with_credit(&mut self, delegator: AccountId, collateral_token: AccountId, amount: Balance) -> Result<()> {
+            let caller = Self::env().caller();
+            let allowance = self.credit_allowance(delegator, caller);
+            if allowance < amount {
+                return Err(Error::InsufficientCredit)
+            }
+            self.borrow_for(delegator, caller, collateral_token, amount)?;
+            self.credit_allowances.insert((delegator, caller), allowance - amount);
+            let delegated = self.delegated_debt_of(delegator, caller);
+            self.delegated_debts.insert((delegator, caller), delegated + amount);
+
+            self.env().emit_event( CreditBorrowed{
+                delegator,
+                delegatee : caller,
+                collateral_token,
+                amount,
+            });
+            Ok(())
+        }
+
+        // 被委托人替委托人归还借款，归还的数量从被委托人借出的本金中扣除，不会恢复信用额度
+        #[ink(message)]
+        pub fn repay_credit(&mut self, delegator: AccountId, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let caller = Self::env().caller();
+            let repaid = self.repay_from(caller, delegator, amount)?;
+            let delegated = self.delegated_debt_of(delegator, caller);
+            if repaid >= delegated {
+                self.delegated_debts.take(&(delegator, caller));
+            } else {
+                self.delegated_debts.insert((delegator, caller), delegated - repaid);
+            }
+
+            self.env().emit_event( CreditRepaid{
+                delegator,
+                delegatee : caller,
+                amount : repaid,
+            });
+            Ok(())
+        }
+
+        // 内部函数，已经收到 amount 数量的基础代币后，从借款人当前的借款 borrowed 中扣除
+        fn reduce_debt(&mut self, borrower: AccountId, borrowed: Balance, amount: Balance) {
+            self.reduce_isolated_debt(borrower, amount);
+            if borrowed == amount {
+                self.borrowings.take(&borrower);
+                self.account_borrow_index.take(&borrower);
+                self.untrack_borrower(borrower);
+            } else {
+                self.borrowings.insert(borrower, borrowed - amount);
+            }
+            // 单个用户的借款按指数取整，总借款可能略小于各用户借款之和
+            self.total_borrowings = self.total_borrowings.saturating_sub(amount);
+            self.borrowings_balance += amount;
+
+            self.env().emit_event( DebtTransfer{
+                from : Some(borrower),
+                to : None,
+                value : amount,
+            });
+        }
+
+        // 设置质押币种的清算奖励，单位为万分之一，只能治理账号调用
+        #[ink(message)]
+        pub fn set_liquidation_bonus(&mut self, token: AccountId, bonus_bps: u32) -> Result<()> {
+            self.ensure_governance()?;
+            if bonus_bps as Balance > RATIO_DENOMINATOR {
+                return Err(Error::InvalidRatio)
+            }
+            match self.collateral_configs.get_mut(&token) {
+                Some(config) => config.liquidation_bonus_bps = bonus_bps,
+                None => return Err(Error::UnsupportedCollateral),
+            }
+            self.emit_params_updated(Param::LiquidationBonus, Some(token));
+            Ok(())
+        }
+
+        // 清算健康系数低于 1 的借款人，清算人替借款人归还最多 repay_amount 的浮动利率和稳定利率借款，
+        // 获得价值为归还数量 × (1 + 清算奖励) 的质押代币，质押不足时按剩余质押减少归还数量
+        #[ink(message)]
+        pub fn liquidate(&mut self, borrower: AccountId, collateral_token: AccountId, repay_amount: Balance) -> Result<()> {
+            if repay_amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.ensure_not_paused(PauseAction::Liquidation)?;
+            if self.config.auction_blocks > 0 {
+                return Err(Error::AuctionModeActive)
+            }
+            let caller = Self::env().caller();
+            if caller == borrower {
+                return Err(Error::SelfLiquidation)
+            }
+            self.ensure_keeper(caller)?;
+            let config = match self.collateral_configs.get(&collateral_token) {
+                Some(config) => *config,
+                None => return Err(Error::UnsupportedCollateral),
+            };
+            let borrowed = self.settle_debt(borrower);
+            let stable_debt = self.settle_stable_debt(borrower);
+            if borrowed + stable_debt == 0 {
+                return Err(Error::NoDebt)
+            }
+            // 仓位已经恢复健康时停止计时，避免再次下跌时沿用之前的计时跳过宽限期
+            if self.health_factor_for(borrower, borrowed)? >= INDEX_PRECISION {
+                self.at_risk_since.take(&borrower);
+                return Err(Error::PositionHealthy)
+            }
+            self.ensure_grace_elapsed(borrower)?;
+
+            let (repay, seized) = self.seize_amounts(
+                borrower, collateral_token, config, core::cmp::min(repay_amount, borrowed + stable_debt)
+            )?;
+
+            // 先更新状态再转账，任何一笔转账失败都回滚本次调用的全部修改
+            self.reduce_variable_and_stable_debt(borrower, borrowed, stable_debt, repay);
+            self.reduce_pledge(borrower, collateral_token, seized);
+            if self.transfer_in(self.base_token_accountid, caller, repay).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+            if self.transfer_out(collateral_token, caller, seized).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+
+            self.env().emit_event( Liquidated{
+                liquidator : caller,
+                borrower,
+                collateral_token,
+                repay_amount : repay,
+                seized_amount : seized,
+                liquidation_bonus_bps : config.liquidation_bonus_bps,
+            });
+            self.clear_risk_if_healthy(borrower);
+            self.record_bad_debt(borrower);
+            self.pay_keeper_reward(caller, borrower, repay);
+            Ok(())
+        }
+
+        // 返回用户记录的坏账数量
+        #[ink(message)]
+        pub fn bad_debt(&self, account: AccountId) -> Balance {
+            self.bad_debts.get(&account).copied().unwrap_or(0)
+        }
+
+        // 返回尚未核销的坏账总和
+        #[ink(message)]
+        pub fn total_bad_debt(&self) -> Balance {
+            self.total_bad_debt
+        }
+
+        // 核销用户的坏账，清除用户全部的借款，
+        // 资金池总价值随之减少，损失通过存款份额的兑换比例由所有存款人分摊，只能管理者调用
+        #[ink(message)]
+        pub fn write_off(&mut self, borrower: AccountId) -> Result<()> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::OnlyForOwner)
+            }
+            if !self.bad_debts.contains_key(&borrower) || self.has_collateral(borrower) {
+                return Err(Error::NoBadDebt)
+            }
+            let borrowed = self.settle_debt(borrower);
+            let stable_debt = self.settle_stable_debt(borrower);
+            let fixed_debt = self.fixed_debt_of(borrower);
+            self.borrowings.take(&borrower);
+            self.account_borrow_index.take(&borrower);
+            self.total_borrowings = self.total_borrowings.saturating_sub(borrowed);
+            self.stable_debts.take(&borrower);
+            self.total_stable_debt = self.total_stable_debt.saturating_sub(stable_debt);
+            if let Some(loan) = self.fixed_loans.take(&borrower) {
+                let principal_left = loan.principal.saturating_sub(loan.repaid);
+                self.total_fixed_principal = self.total_fixed_principal.saturating_sub(principal_left);
+            }
+            self.untrack_borrower(borrower);
+            self.at_risk_since.take(&borrower);
+
+            if borrowed > 0 {
+                self.env().emit_event( DebtTransfer{
+                    from : Some(borrower),
+                    to : None,
+                    value : borrowed,
+                });
+            }
+            self.env().emit_event( BadDebtWrittenOff{
+                borrower,
+                amount : borrowed + stable_debt + fixed_debt,
+            });
+            Ok(())
+        }
+
+        // 内部函数，清算后借款人在所有支持的质押币种上都没有质押但仍有借款时，把剩余借款记为坏账
+        fn record_bad_debt(&mut self, borrower: AccountId) {
+            if self.has_collateral(borrower) {
+                return
+            }
+            let debt = self.accrued_debt_of(borrower) + self.other_debt_of(borrower);
+            if debt == 0 {
+                return
+            }
+            let recorded = self.bad_debt(borrower);
+            self.bad_debts.insert(borrower, debt);
+            self.total_bad_debt = self.total_bad_debt - recorded + debt;
+            self.env().emit_event( BadDebtRecorded{
+                borrower,
+                amount : debt,
+            });
+        }
+
+        // 内部函数，返回用户在支持的质押币种上是否还有质押
+        fn has_collateral(&self, account: AccountId) -> bool {
+            self.collateral_configs
+                .keys()
+                .any(|token| self.pledge_of(account, *token) > 0)
+        }
+
+        // 返回借款人的不健康仓位开始计时的区块，None 表示没有在计时
+        #[ink(message)]
+        pub fn at_risk_since(&self, borrower: AccountId) -> Option<BlockNumber> {
+            self.at_risk_since.get(&borrower).copied()
+        }
+
+        // 任何人都可以为不健康的仓位开始宽限期计时，宽限期结束后仓位才能被清算
+        // 仓位已经恢复健康时停止之前的计时，再次不健康时需要重新开始计时
+        #[ink(message)]
+        pub fn flag_at_risk(&mut self, borrower: AccountId) -> Result<()> {
+            let borrowed = self.settle_debt(borrower);
+            let health_factor = self.health_factor_for(borrower, borrowed)?;
+            if health_factor >= INDEX_PRECISION {
+                self.at_risk_since.take(&borrower);
+                return Err(Error::PositionHealthy)
+            }
+            if self.at_risk_since.contains_key(&borrower) {
+                return Err(Error::GracePeriodActive)
+            }
+            let block_number = self.env().block_number();
+            self.at_risk_since.insert(borrower, block_number);
+            self.env().emit_event( PositionAtRisk{
+                borrower,
+                health_factor,
+                liquidatable_at : block_number + self.config.grace_blocks,
+            });
+            Ok(())
+        }
+
+        // 内部函数，宽限期不为 0 时，仓位需要已经开始计时并且等待了 grace_blocks 个区块才能被清算
+        fn ensure_grace_elapsed(&self, borrower: AccountId) -> Result<()> {
+            if self.config.grace_blocks == 0 {
+                return Ok(())
+            }
+            match self.at_risk_since.get(&borrower) {
+                Some(since) if self.env().block_number() >= since + self.config.grace_blocks => Ok(()),
+                _ => Err(Error::GracePeriodActive),
+            }
+        }
+
+        // 内部函数，借款人补充质押或者还款后仓位恢复健康时，停止宽限期计时
+        fn clear_risk_if_healthy(&mut self, account: AccountId) {
+            if !self.at_risk_since.contains_key(&account) {
+                return
+            }
+            let debt = self.accrued_debt_of(account);
+            if let Ok(health_factor) = self.health_factor_for(account, debt) {
+                if health_factor >= INDEX_PRECISION {
+                    self.at_risk_since.take(&account);
+                }
+            }
+        }
+
+        // 内部函数，计算清算时实际归还的数量和扣除的质押数量，质押代币价值为归还数量 × (1 + 清算奖励)
+        // 质押不足时扣除全部质押，并按质押价值减少归还数量
+        fn seize_amounts(&self, borrower: AccountId, collateral_token: AccountId, config: CollateralConfig, repay: Balance) -> Result<(Balance, Balance)> {
+            let price = self.price_of(collateral_token)?;
+            let pledged = self.pledge_of(borrower, collateral_token);
+            let bonus = RATIO_DENOMINATOR + config.liquidation_bonus_bps as Balance;
+            let mut repay = repay;
+            let mut seized = repay * bonus / RATIO_DENOMINATOR * PRICE_PRECISION / price.max(1);
+            if seized > pledged {
+                seized = pledged;
+                repay = pledged * price / PRICE_PRECISION * RATIO_DENOMINATOR / bonus;
+            }
+            if repay == 0 || seized == 0 {
+                return Err(Error::InsufficientPledge)
+            }
+            Ok((repay, seized))
+        }
+
+        // 返回用户的固定期限借款
+        #[ink(message)]
+        pub fn fixed_loan_of(&self, account: AccountId) -> Option<FixedLoan> {
+            self.fixed_loans.get(&account).copied()
+        }
+
+        // 返回用户固定期限借款截至当前区块需要归还的数量，包括利息和逾期的滞纳金
+        // 利息只计算到到期区块，逾期第 n 个区块的滞纳金为本金 × 滞纳金费率 × n
+        #[ink(message)]
+        pub fn fixed_debt_of(&self, account: AccountId) -> Balance {
+            let loan = match self.fixed_loans.get(&account) {
+                Some(loan) => *loan,
+                None => return 0,
+            };
+            let block_number = self.env().block_number();
+            let interest_blocks = core::cmp::min(block_number, loan.maturity_block) - loan.start_block;
+            let interest = loan.principal.saturating_mul(loan.rate * interest_blocks as u128) / INDEX_PRECISION;
+            let overdue = block_number.saturating_sub(loan.maturity_block) as u128;
+            let late_factor = self.config.late_fee_rate.saturating_mul(overdue * (overdue + 1) / 2);
+            let late_fee = loan.principal.saturating_mul(late_factor) / INDEX_PRECISION;
+            (loan.principal + interest).saturating_add(late_fee).saturating_sub(loan.repaid)
+        }
+
+        // 内部函数，返回用户浮动利率借款以外的借款，即稳定利率借款和固定期限借款之和
+        fn other_debt_of(&self, account: AccountId) -> Balance {
+            self.stable_debt_of(account) + self.fixed_debt_of(account)
+        }
+
+        // 内部函数，返回用户的固定期限借款是否已经逾期
+        fn is_fixed_loan_overdue(&self, account: AccountId) -> bool {
+            match self.fixed_loans.get(&account) {
+                Some(loan) => self.env().block_number() > loan.maturity_block,
+                None => false,
+            }
+        }
+
+        // 借出一笔固定期限借款，按当前的固定利率计息，term_blocks 个区块后到期
+        #[ink(message)]
+        pub fn borrow_fixed(&mut self, collateral_token: AccountId, amount: Balance, term_blocks: BlockNumber) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            if term_blocks == 0 {
+                return Err(Error::InvalidTerm)
+            }
+            self.ensure_not_paused(PauseAction::Borrow)?;
+            if !self.collateral_configs.contains_key(&collateral_token) {
+                return Err(Error::UnsupportedCollateral)
+            }
+            let caller = Self::env().caller();
+            if self.fixed_loans.contains_key(&caller) {
+                return Err(Error::FixedLoanExists)
+            }
+            let borrowed = self.settle_debt(caller) + self.stable_debt_of(caller);
+            if borrowed + amount > self.borrowing_power(caller)? {
+                return Err(Error::InsufficientCollateral)
+            }
+            self.ensure_tier_limit(caller, borrowed, amount)?;
+            self.ensure_isolation_ceiling(caller, amount)?;
+            if amount > self.borrowings_balance {
+                return Err(Error::InsufficientLiquidity)
+            }
+            let total_borrowed = self.total_debt();
+            if exceeds(self.config.borrow_cap, total_borrowed + amount) {
+                return Err(Error::CapReached)
+            }
+            self.transfer_out(self.base_token_accountid, caller, amount)?;
+
+            let block_number = self.env().block_number();
+            let discount_bps = self.tier_config_of(caller)
+                .map(|config| config.rate_discount_bps)
+                .unwrap_or(0);
+            let loan = FixedLoan {
+                principal: amount,
+                rate: self.config.fixed_rate - self.config.fixed_rate * discount_bps as u128 / RATIO_DENOMINATOR,
+                start_block: block_number,
+                maturity_block: block_number + term_blocks,
+                repaid: 0,
+            };
+            self.fixed_loans.insert(caller, loan);
+            self.track_borrower(caller);
+            self.add_isolated_debt(caller, amount);
+            self.total_fixed_principal += amount;
+            self.borrowings_balance -= amount;
+
+            self.env().emit_event( FixedBorrowed{
+                account : caller,
+                amount,
+                rate : loan.rate,
+                maturity_block : loan.maturity_block,
+            });
+            Ok(())
+        }
+
+        // 归还固定期限借款，超过需要归还数量的部分不会被转入，还清后可以再借新的固定期限借款
+        #[ink(message)]
+        pub fn repay_fixed(&mut self, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let caller = Self::env().caller();
+            let debt = self.fixed_debt_of(caller);
+            if debt == 0 {
+                return Err(Error::NoDebt)
+            }
+            let amount = core::cmp::min(amount, debt);
+            self.transfer_in(self.base_token_accountid, caller, amount)?;
+            self.reduce_fixed_debt(caller, debt, amount);
+            self.clear_risk_if_healthy(caller);
+
+            self.env().emit_event( FixedRepaid{
+                account : caller,
+                amount,
+                remaining : debt - amount,
+            });
+            Ok(())
+        }
+
+        // 内部函数，已经收到 amount 数量的基础代币后，从借款人的固定期限借款 debt 中扣除，先归还本金
+        fn reduce_fixed_debt(&mut self, borrower: AccountId, debt: Balance, amount: Balance) {
+            let mut loan = match self.fixed_loans.get(&borrower) {
+                Some(loan) => *loan,
+                None => return,
+            };
+            self.reduce_isolated_debt(borrower, amount);
+            let principal_left = loan.principal.saturating_sub(loan.repaid);
+            let principal_repaid = core::cmp::min(principal_left, amount);
+            self.total_fixed_principal -= principal_repaid;
+            self.borrowings_balance += amount;
+            if amount == debt {
+                self.fixed_loans.take(&borrower);
+                self.untrack_borrower(borrower);
+            } else {
+                loan.repaid += amount;
+                self.fixed_loans.insert(borrower, loan);
+            }
+        }
+
+        // 清算固定期限借款，借款逾期后不论健康系数都可以被清算，未逾期时健康系数低于 1 才能清算
+        #[ink(message)]
+        pub fn liquidate_fixed(&mut self, borrower: AccountId, collateral_token: AccountId, repay_amount: Balance) -> Result<()> {
+            if repay_amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.ensure_not_paused(PauseAction::Liquidation)?;
+            if self.config.auction_blocks > 0 {
+                return Err(Error::AuctionModeActive)
+            }
+            let caller = Self::env().caller();
+            if caller == borrower {
+                return Err(Error::SelfLiquidation)
+            }
+            self.ensure_keeper(caller)?;
+            let config = match self.collateral_configs.get(&collateral_token) {
+                Some(config) => *config,
+                None => return Err(Error::UnsupportedCollateral),
+            };
+            let debt = self.fixed_debt_of(borrower);
+            if debt == 0 {
+                return Err(Error::NoDebt)
+            }
+            let borrowed = self.settle_debt(borrower);
+            if !self.is_fixed_loan_overdue(borrower) {
+                if self.health_factor_for(borrower, borrowed)? >= INDEX_PRECISION {
+                    self.at_risk_since.take(&borrower);
+                    return Err(Error::PositionHealthy)
+                }
+                self.ensure_grace_elapsed(borrower)?;
+            }
+            let (repay, seized) = self.seize_amounts(
+                borrower, collateral_token, config, core::cmp::min(repay_amount, debt)
+            )?;
+
+            // 与 liquidate 相同，先更新状态再转账，转账失败时回滚
+            self.reduce_fixed_debt(borrower, debt, repay);
+            self.reduce_pledge(borrower, collateral_token, seized);
+            if self.transfer_in(self.base_token_accountid, caller, repay).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+            if self.transfer_out(collateral_token, caller, seized).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+
+            self.env().emit_event( Liquidated{
+                liquidator : caller,
+                borrower,
+                collateral_token,
+                repay_amount : repay,
+                seized_amount : seized,
+                liquidation_bonus_bps : config.liquidation_bonus_bps,
+            });
+            self.clear_risk_if_healthy(borrower);
+            self.record_bad_debt(borrower);
+            self.pay_keeper_reward(caller, borrower, repay);
+            Ok(())
+        }
+
+        // 闪电贷，把 amount 数量的基础代币转给接收合约并调用它的 on_flash_loan，
+        // 调用结束后从接收合约转回借款数量加手续费，手续费全部计入协议储备金
+        // 接收合约拒绝或者没有归还时整个调用回滚，借出的代币也会一起退回
+        #[ink(message)]
+        pub fn flash_loan(&mut self, amount: Balance, receiver: AccountId, data: Vec<u8>) -> Result<()> {
+            if amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.ensure_not_paused(PauseAction::Borrow)?;
+            if amount > self.borrowings_balance {
+                return Err(Error::InsufficientLiquidity)
+            }
+            let caller = Self::env().caller();
+            let token = self.base_token_accountid;
+            let fee = amount * self.config.flash_loan_fee_bps as Balance / RATIO_DENOMINATOR;
+            self.transfer_out(token, receiver, amount)?;
+
+            let accepted = build_call::<Environment>()
+                .callee(receiver)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ON_FLASH_LOAN_SELECTOR))
+                        .push_arg(caller)
+                        .push_arg(token)
+                        .push_arg(amount)
+                        .push_arg(fee)
+                        .push_arg(data)
+                )
+                .returns::<ReturnType<bool>>()
+                .fire();
+            if !matches!(accepted, Ok(true)) {
+                Self::revert(Error::FlashLoanNotRepaid)
+            }
+            if self.transfer_in(token, receiver, amount + fee).is_err() {
+                Self::revert(Error::FlashLoanNotRepaid)
+            }
+            self.borrowings_balance += fee;
+            self.protocol_reserves += fee;
+
+            self.env().emit_event( FlashLoan{
+                initiator : caller,
+                receiver,
+                amount,
+                fee,
+            });
+            Ok(())
+        }
+
+        // 返回借款人指定质押币种的拍卖
+        #[ink(message)]
+        pub fn auction_of(&self, borrower: AccountId, collateral_token: AccountId) -> Option<Auction> {
+            self.auctions.get(&(borrower, collateral_token)).copied()
+        }
+
+        // 返回拍卖的当前价格，没有拍卖时返回 None
+        #[ink(message)]
+        pub fn auction_price(&self, borrower: AccountId, collateral_token: AccountId) -> Option<u128> {
+            self.auctions
+                .get(&(borrower, collateral_token))
+                .map(|auction| self.current_auction_price(auction))
+        }
+
+        // 内部函数，按经过的区块数线性计算拍卖的当前价格
+        fn current_auction_price(&self, auction: &Auction) -> u128 {
+            let block_number = self.env().block_number();
+            if block_number >= auction.end_block {
+                return auction.end_price
+            }
+            let elapsed = (block_number - auction.start_block) as u128;
+            let duration = (auction.end_block - auction.start_block) as u128;
+            auction.start_price - (auction.start_price - auction.end_price) * elapsed / duration
+        }
+
+        // 开启拍卖清算后，任何人都可以把不健康仓位的一种质押全部放入荷兰式拍卖，
+        // 拍卖中的质押不再计入借款人的借款额度，起始价格和最低价格按当前价格乘以 config 中的比例计算
+        #[ink(message)]
+        pub fn start_auction(&mut self, borrower: AccountId, collateral_token: AccountId) -> Result<()> {
+            self.ensure_not_paused(PauseAction::Liquidation)?;
+            if self.config.auction_blocks == 0 {
+                return Err(Error::AuctionDisabled)
+            }
+            if self.auctions.contains_key(&(borrower, collateral_token)) {
+                return Err(Error::AuctionExists)
+            }
+            let pledged = self.pledge_of(borrower, collateral_token);
+            if pledged == 0 {
+                return Err(Error::InsufficientPledge)
+            }
+            let borrowed = self.settle_debt(borrower);
+            if borrowed + self.other_debt_of(borrower) == 0 {
+                return Err(Error::NoDebt)
+            }
+            if !self.is_fixed_loan_overdue(borrower) {
+                if self.health_factor_for(borrower, borrowed)? >= INDEX_PRECISION {
+                    self.at_risk_since.take(&borrower);
+                    return Err(Error::PositionHealthy)
+                }
+                self.ensure_grace_elapsed(borrower)?;
+            }
+            let price = self.price_of(collateral_token)?;
+            let block_number = self.env().block_number();
+            let auction = Auction {
+                amount: pledged,
+                start_block: block_number,
+                end_block: block_number + self.config.auction_blocks,
+                start_price: price * self.config.auction_start_bps as u128 / RATIO_DENOMINATOR,
+                end_price: price * self.config.auction_end_bps as u128 / RATIO_DENOMINATOR,
+            };
+            self.reduce_pledge(borrower, collateral_token, pledged);
+            self.auctions.insert((borrower, collateral_token), auction);
+
+            self.env().emit_event( AuctionStarted{
+                borrower,
+                collateral_token,
+                amount : pledged,
+                start_price : auction.start_price,
+                end_price : auction.end_price,
+                end_block : auction.end_block,
+            });
+            Ok(())
+        }
+
+        // 按拍卖的当前价格买入最多 max_amount 数量的质押，支付的基础代币依次归还浮动利率、稳定利率和固定期限借款，
+        // 借款全部还清后拍卖结束，剩余的质押退回借款人，多付的基础代币转给借款人
+        #[ink(message)]
+        pub fn bid(&mut self, borrower: AccountId, collateral_token: AccountId, max_amount: Balance) -> Result<()> {
+            if max_amount == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            self.ensure_not_paused(PauseAction::Liquidation)?;
+            let caller = Self::env().caller();
+            if caller == borrower {
+                return Err(Error::SelfLiquidation)
+            }
+            self.ensure_keeper(caller)?;
+            let mut auction = match self.auctions.get(&(borrower, collateral_token)) {
+                Some(auction) => *auction,
+                None => return Err(Error::NoAuction),
+            };
+            let price = self.current_auction_price(&auction);
+            let borrowed = self.settle_debt(borrower);
+            let stable_debt = self.settle_stable_debt(borrower);
+            let fixed_debt = self.fixed_debt_of(borrower);
+            let debt = borrowed + stable_debt + fixed_debt;
+
+            let mut amount = core::cmp::min(max_amount, auction.amount);
+            let mut cost = (amount * price).div_ceil(PRICE_PRECISION);
+            if cost >= debt && price > 0 {
+                // 只需要买入刚好还清借款的数量
+                amount = core::cmp::min((debt * PRICE_PRECISION).div_ceil(price), amount);
+                cost = (amount * price).div_ceil(PRICE_PRECISION);
+            }
+            if amount == 0 || cost == 0 {
+                return Err(Error::ZeroAmount)
+            }
+            let repay = core::cmp::min(cost, debt);
+            let surplus = cost - repay;
+            let debt_cleared = repay == debt;
+            let returned = if debt_cleared { auction.amount - amount } else { 0 };
+
+            let fixed_repay = repay.saturating_sub(borrowed + stable_debt);
+            self.reduce_variable_and_stable_debt(borrower, borrowed, stable_debt, repay - fixed_repay);
+            if fixed_repay > 0 {
+                self.reduce_fixed_debt(borrower, fixed_debt, fixed_repay);
+            }
+            auction.amount -= amount;
+            self.env().emit_event( AuctionBid{
+                borrower,
+                buyer : caller,
+                collateral_token,
+                amount,
+                cost,
+            });
+
+            if debt_cleared || auction.amount == 0 {
+                self.end_auction(borrower, collateral_token, returned, surplus);
+                self.record_bad_debt(borrower);
+            } else {
+                self.auctions.insert((borrower, collateral_token), auction);
+            }
+
+            // 状态更新完成后再转账，任何一笔转账失败都回滚本次出价
+            if self.transfer_in(self.base_token_accountid, caller, cost).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+            if self.transfer_out(collateral_token, caller, amount).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+            if surplus > 0 && self.transfer_out(self.base_token_accountid, borrower, surplus).is_err() {
+                Self::revert(Error::TokenTransferFailed)
+            }
+            self.pay_keeper_reward(caller, borrower, repay);
+            Ok(())
+        }
+
+        // 借款人在拍卖期间自己还清了借款时，任何人都可以结束拍卖，剩余的质押全部退回借款人
+        #[ink(message)]
+        pub fn cancel_auction(&mut self, borrower: AccountId, collateral_token: AccountId) -> Result<()> {
+            let auction = match self.auctions.get(&(borrower, collateral_token)) {
+                Some(auction) => *auction,
+                None => return Err(Error::NoAuction),
+            };
+            if self.settle_debt(borrower) + self.other_debt_of(borrower) > 0 {
+                return Err(Error::AuctionExists)
+            }
+            self.end_auction(borrower, collateral_token, auction.amount, 0);
+            Ok(())
+        }
+
+        // 内部函数，结束拍卖，把 returned 数量的质押退回借款人
+        fn end_auction(&mut self, borrower: AccountId, collateral_token: AccountId, returned: Balance, surplus: Balance) {
+            self.auctions.take(&(borrower, collateral_token));
+            if returned > 0 {
+                let pledged = self.pledge_of(borrower, collateral_token);
+                self.pledges.insert((borrower, collateral_token), pledged + returned);
+                let total_pledged = self.total_pledged_of(collateral_token);
+                self.total_pledged.insert(collateral_token, total_pledged + returned);
+            }
+            self.env().emit_event( AuctionEnded{
+                borrower,
+                collateral_token,
+                returned,
+                surplus,
+            });
+            self.clear_risk_if_healthy(borrower);
+        }
+
+        // 内部函数，返回错误并回滚本次调用的全部状态修改，用于已经转出代币之后才发现失败的情况
+        fn revert(error: Error) -> ! {
+            ink_env::return_value::<Result<()>>(ReturnFlags::default().set_reverted(true), &Err(error))
+        }
+
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        use ink_lang as ink;
+
+        // 模拟的 ERC20 账本，基础代币和质押代币都记在这里，按代币地址区分
+        // 每个测试运行在单独的线程中，账本互不影响
+        pub mod mock_erc20 {
+            use super::{AccountId, Balance};
+            use std::{cell::RefCell, collections::HashMap};
+
+            thread_local! {
+                // (代币, 账号) -> 余额
+                static BALANCES: RefCell<HashMap<(AccountId, AccountId), Balance>> = RefCell::new(HashMap::new());
+                // (代币, 拥有者, 被授权者) -> 授权数量
+                static ALLOWANCES: RefCell<HashMap<(AccountId, AccountId, AccountId), Balance>> = RefCell::new(HashMap::new());
+            }
+
+            pub fn balance_of(token: AccountId, account: AccountId) -> Balance {
+                BALANCES.with(|balances| balances.borrow().get(&(token, account)).copied().unwrap_or(0))
+            }
+
+            pub fn mint(token: AccountId, account: AccountId, amount: Balance) {
+                let balance = balance_of(token, account);
+                BALANCES.with(|balances| balances.borrow_mut().insert((token, account), balance + amount));
+            }
+
+            pub fn approve(token: AccountId, owner: AccountId, spender: AccountId, amount: Balance) {
+                ALLOWANCES.with(|allowances| allowances.borrow_mut().insert((token, owner, spender), amount));
+            }
+
+            pub fn transfer(token: AccountId, from: AccountId, to: AccountId, amount: Balance) -> bool {
+                let from_balance = balance_of(token, from);
+                if from_balance < amount {
+                    return false
+                }
+                BALANCES.with(|balances| balances.borrow_mut().insert((token, from), from_balance - amount));
+                mint(token, to, amount);
+                true
+            }
+
+            pub fn transfer_from(token: AccountId, spender: AccountId, from: AccountId, to: AccountId, amount: Balance) -> bool {
+                let allowance = ALLOWANCES.with(|allowances| {
+                    allowances.borrow().get(&(token, from, spender)).copied().unwrap_or(0)
+                });
+                if allowance < amount || !transfer(token, from, to, amount) {
+                    return false
+                }
+                approve(token, from, spender, allowance - amount);
+                true
+            }
+        }
+
+        // 模拟运行时通过链扩展返回的价格，没有设置价格的币种读取失败
+        pub mod mock_price {
+            use super::AccountId;
+            use std::{cell::RefCell, collections::HashMap};
+
+            thread_local! {
+                // 质押币种 -> 价格
+                static PRICES: RefCell<HashMap<AccountId, u128>> = RefCell::new(HashMap::new());
+            }
+
+            pub fn price_of(token: AccountId) -> Option<u128> {
+                PRICES.with(|prices| prices.borrow().get(&token).copied())
+            }
+
+            pub fn set_price(token: AccountId, price: u128) {
+                PRICES.with(|prices| prices.borrow_mut().insert(token, price));
+            }
+        }
+
+        fn accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
+            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts")
+        }
+
+        // 合约地址，模拟账本中的转入转出都以它为合约账号
+        fn contract_id() -> AccountId {
+            ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .unwrap_or([0x0; 32].into())
+        }
+
+        fn base_token() -> AccountId {
+            AccountId::from([0x10; 32])
+        }
+
+        fn collateral_token() -> AccountId {
+            AccountId::from([0x20; 32])
+        }
+
+        // 以 caller 的身份调用合约
+        fn call_as<R>(caller: AccountId, f: impl FnOnce() -> R) -> R {
+            let data = ink_env::test::CallData::new(ink_env::call::Selector::new([0x00; 4]));
+            ink_env::test::push_execution_context::<ink_env::DefaultEnvironment>(
+                caller,
+                contract_id(),
+                1000000,
+                1000000,
+                data,
+            );
+            let result = f();
+            ink_env::test::pop_execution_context();
+            result
+        }
+
+        // 给账号发放代币并授权给合约
+        fn fund(token: AccountId, account: AccountId, amount: Balance) {
+            mock_erc20::mint(token, account, amount);
+            mock_erc20::approve(token, account, contract_id(), Balance::MAX);
+        }
+
+        fn advance_blocks(blocks: u32) {
+            for _ in 0..blocks {
+                ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot advance block");
+            }
+        }
+
+        // 部署合约，Alice 为管理者并充值 10_000 个基础代币，质押币种的质押率 50%，清算阈值 80%，清算奖励 5%，Bob 持有 1_000 个质押代币
+        fn setup() -> Loan {
+            mock_price::set_price(collateral_token(), PRICE_PRECISION);
+            let accounts = accounts();
+            let mut loan = Loan::new(base_token());
+            fund(base_token(), accounts.alice, 10_000);
+            assert_eq!(loan.recharge_for_borrowing(10_000), Ok(()));
+            let config = CollateralConfig {
+                collateral_factor_bps: 5_000,
+                liquidation_threshold_bps: 8_000,
+                liquidation_bonus_bps: 500,
+            };
+            assert_eq!(loan.set_collateral_config(collateral_token(), config), Ok(()));
+            fund(collateral_token(), accounts.bob, 1_000);
+            // Bob 借出的基础代币用于还款，也需要授权给合约
+            fund(base_token(), accounts.bob, 0);
+            loan
+        }
+
+        // 每个区块 0.1% 的借款利率，不随利用率变化
+        fn fixed_rate_config() -> Config {
+            Config {
+                base_rate: INDEX_PRECISION / 1_000,
+                ..Config::default()
+            }
+        }
+
+        #[ink::test]
+        fn recharge_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(loan.borrowings_balance(), 10_000);
+            assert_eq!(loan.supply_shares_of(accounts.alice), 10_000);
+            assert_eq!(mock_erc20::balance_of(base_token(), contract_id()), 10_000);
+
+            // 只有管理者可以充值
+            fund(base_token(), accounts.bob, 100);
+            assert_eq!(
+                call_as(accounts.bob, || loan.recharge_for_borrowing(100)),
+                Err(Error::OnlyForOwner)
+            );
+            // 没有足够的代币时转账失败，状态不变
+            assert_eq!(loan.recharge_for_borrowing(1), Err(Error::TokenTransferFailed));
+            assert_eq!(loan.borrowings_balance(), 10_000);
+        }
+
+        #[ink::test]
+        fn pledge_and_borrow_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(loan.pledge_of(accounts.bob, collateral_token()), 1_000);
+            assert_eq!(loan.total_pledged_of(collateral_token()), 1_000);
+            assert_eq!(mock_erc20::balance_of(collateral_token(), accounts.bob), 0);
+
+            // 质押率 50%，最多借出 500
+            assert_eq!(
+                call_as(accounts.bob, || loan.borrow(collateral_token(), 501)),
+                Err(Error::InsufficientCollateral)
+            );
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 400)), Ok(()));
+            assert_eq!(loan.borrowing_of(accounts.bob), 400);
+            assert_eq!(loan.borrowings_balance(), 9_600);
+            assert_eq!(mock_erc20::balance_of(base_token(), accounts.bob), 400);
+            assert_eq!(loan.borrowers(0, 10), vec![accounts.bob]);
+
+            // 剩余质押需要满足借款比例
+            assert_eq!(
+                call_as(accounts.bob, || loan.withdraw_collateral(collateral_token(), 201)),
+                Err(Error::WouldBeUndercollateralized)
+            );
+            assert_eq!(call_as(accounts.bob, || loan.withdraw_collateral(collateral_token(), 200)), Ok(()));
+            assert_eq!(loan.pledge_of(accounts.bob, collateral_token()), 800);
+
+            // 不支持的币种不能质押
+            assert_eq!(
+                call_as(accounts.bob, || loan.pledge(base_token(), 1)),
+                Err(Error::UnsupportedCollateral)
+            );
+        }
+
+        #[ink::test]
+        fn repay_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(call_as(accounts.bob, || loan.repay(100)), Err(Error::NoDebt));
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 400)), Ok(()));
+
+            assert_eq!(call_as(accounts.bob, || loan.repay(150)), Ok(()));
+            assert_eq!(loan.borrowing_of(accounts.bob), 250);
+            assert_eq!(loan.borrowings_balance(), 9_750);
+
+            // 替别人还款使用调用者的代币
+            fund(base_token(), accounts.charlie, 50);
+            assert_eq!(call_as(accounts.charlie, || loan.repay_on_behalf(accounts.bob, 50)), Ok(()));
+            assert_eq!(loan.borrowing_of(accounts.bob), 200);
+            assert_eq!(mock_erc20::balance_of(base_token(), accounts.charlie), 0);
+
+            assert_eq!(call_as(accounts.bob, || loan.repay_all()), Ok(()));
+            assert_eq!(loan.borrowing_of(accounts.bob), 0);
+            assert_eq!(loan.total_borrowings(), 0);
+            assert_eq!(loan.borrowings_balance(), 10_000);
+            assert_eq!(loan.borrower_count(), 0);
+            assert_eq!(call_as(accounts.bob, || loan.withdraw_collateral(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(mock_erc20::balance_of(collateral_token(), accounts.bob), 1_000);
+        }
+
+        #[ink::test]
+        fn close_position_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(loan.update_config(fixed_rate_config()), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 400)), Ok(()));
+            advance_blocks(10);
+            fund(base_token(), accounts.bob, 4);
+
+            assert_eq!(call_as(accounts.bob, || loan.close_position()), Ok(()));
+            assert_eq!(loan.current_debt(accounts.bob), 0);
+            assert_eq!(loan.pledge_of(accounts.bob, collateral_token()), 0);
+            assert_eq!(mock_erc20::balance_of(base_token(), accounts.bob), 0);
+            assert_eq!(mock_erc20::balance_of(collateral_token(), accounts.bob), 1_000);
+        }
+
+        #[ink::test]
+        fn rate_mode_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(loan.update_config(fixed_rate_config()), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(
+                call_as(accounts.bob, || loan.borrow_with_mode(collateral_token(), 400, RateMode::Stable)),
+                Ok(())
+            );
+            assert_eq!(loan.stable_debt(accounts.bob).map(|debt| debt.rate), Some(INDEX_PRECISION / 1_000));
+            assert_eq!(loan.borrowing_of(accounts.bob), 0);
+            // 稳定利率借款同样占用借款额度
+            assert_eq!(
+                call_as(accounts.bob, || loan.borrow(collateral_token(), 101)),
+                Err(Error::InsufficientCollateral)
+            );
+
+            // 之后修改利率模型不影响已有的稳定利率借款
+            let config = Config { base_rate: INDEX_PRECISION / 100, ..Config::default() };
+            assert_eq!(loan.update_config(config), Ok(()));
+            advance_blocks(10);
+            assert_eq!(loan.stable_debt_of(accounts.bob), 404);
+
+            // 切换为浮动利率后按新的利率计息
+            assert_eq!(call_as(accounts.bob, || loan.swap_rate_mode()), Ok(()));
+            assert_eq!(loan.stable_debt_of(accounts.bob), 0);
+            assert_eq!(loan.borrowing_of(accounts.bob), 404);
+            assert_eq!(call_as(accounts.bob, || loan.swap_rate_mode()), Ok(()));
+            assert_eq!(loan.stable_debt(accounts.bob).map(|debt| debt.rate), Some(INDEX_PRECISION / 100));
+
+            fund(base_token(), accounts.bob, 4);
+            assert_eq!(call_as(accounts.bob, || loan.repay_stable(1_000)), Ok(()));
+            assert_eq!(loan.stable_debt_of(accounts.bob), 0);
+            assert_eq!(loan.borrower_count(), 0);
+        }
+
+        #[ink::test]
+        fn interest_accrues() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(loan.update_config(fixed_rate_config()), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 400)), Ok(()));
+
+            advance_blocks(10);
+            assert_eq!(loan.current_debt(accounts.bob), 404);
+            // 查询不会修改存储
+            assert_eq!(loan.borrowing_of(accounts.bob), 400);
+
+            fund(base_token(), accounts.bob, 4);
+            assert_eq!(call_as(accounts.bob, || loan.repay_all()), Ok(()));
+            assert_eq!(loan.current_debt(accounts.bob), 0);
+            // 利息归存款人所有
+            assert_eq!(loan.supply_balance_of(accounts.alice), 10_004);
+        }
+
+        #[ink::test]
+        fn liquidate_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 500)), Ok(()));
+            fund(base_token(), accounts.charlie, 1_000);
+
+            // 健康的仓位不能被清算
+            assert_eq!(
+                call_as(accounts.charlie, || loan.liquidate(accounts.bob, collateral_token(), 100)),
+                Err(Error::PositionHealthy)
+            );
+
+            // 价格下跌 50%，健康系数 = 500 × 80% / 500 = 0.8
+            mock_price::set_price(collateral_token(), PRICE_PRECISION / 2);
+            assert_eq!(loan.health_factor(accounts.bob), Ok(INDEX_PRECISION * 8 / 10));
+            assert_eq!(
+                call_as(accounts.bob, || loan.liquidate(accounts.bob, collateral_token(), 100)),
+                Err(Error::SelfLiquidation)
+            );
+
+            // 归还 100，获得价值 105 的质押代币，即 210 个
+            assert_eq!(call_as(accounts.charlie, || loan.liquidate(accounts.bob, collateral_token(), 100)), Ok(()));
+            assert_eq!(loan.borrowing_of(accounts.bob), 400);
+            assert_eq!(loan.pledge_of(accounts.bob, collateral_token()), 790);
+            assert_eq!(mock_erc20::balance_of(base_token(), accounts.charlie), 900);
+            assert_eq!(mock_erc20::balance_of(collateral_token(), accounts.charlie), 210);
+        }
+
+        #[ink::test]
+        fn repay_with_collateral_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 500)), Ok(()));
+            assert_eq!(
+                call_as(accounts.bob, || loan.repay_with_collateral(collateral_token(), 1_001)),
+                Err(Error::InsufficientPledge)
+            );
+
+            // 价格为 2 时 100 个质押可以归还 200
+            mock_price::set_price(collateral_token(), PRICE_PRECISION * 2);
+            assert_eq!(call_as(accounts.bob, || loan.repay_with_collateral(collateral_token(), 100)), Ok(100));
+            assert_eq!(loan.borrowing_of(accounts.bob), 300);
+            assert_eq!(loan.pledge_of(accounts.bob, collateral_token()), 900);
+            assert_eq!(loan.protocol_collateral_of(collateral_token()), 100);
+            assert_eq!(loan.borrowings_balance(), 9_500);
+
+            // 超过借款的部分不会被使用
+            assert_eq!(call_as(accounts.bob, || loan.repay_with_collateral(collateral_token(), 900)), Ok(150));
+            assert_eq!(loan.borrowing_of(accounts.bob), 0);
+            assert_eq!(loan.pledge_of(accounts.bob, collateral_token()), 750);
+            assert_eq!(loan.borrower_count(), 0);
+
+            assert_eq!(
+                call_as(accounts.bob, || loan.withdraw_protocol_collateral(collateral_token(), accounts.bob, 250)),
+                Err(Error::OnlyForOwner)
+            );
+            assert_eq!(loan.withdraw_protocol_collateral(collateral_token(), accounts.alice, 250), Ok(()));
+            assert_eq!(mock_erc20::balance_of(collateral_token(), accounts.alice), 250);
+            assert_eq!(loan.protocol_collateral_of(collateral_token()), 0);
+
+            // 补回之前用质押归还的借款仍然计入资金池价值
+            assert_eq!(loan.pending_replenishment(), 500);
+            assert_eq!(loan.exchange_rate(), INDEX_PRECISION);
+            fund(base_token(), accounts.alice, 500);
+            assert_eq!(loan.replenish(500), Ok(()));
+            assert_eq!(loan.pending_replenishment(), 0);
+            assert_eq!(loan.borrowings_balance(), 10_000);
+            assert_eq!(loan.exchange_rate(), INDEX_PRECISION);
+        }
+
+        #[ink::test]
+        fn keeper_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            let config = Config { min_keeper_stake: 100, keeper_reward_bps: 1_000, ..Config::default() };
+            assert_eq!(loan.update_config(config), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 500)), Ok(()));
+            mock_price::set_price(collateral_token(), PRICE_PRECISION / 2);
+            fund(base_token(), accounts.charlie, 1_000);
+
+            // 没有质押时不能清算
+            assert_eq!(
+                call_as(accounts.charlie, || loan.liquidate(accounts.bob, collateral_token(), 100)),
+                Err(Error::NotKeeper)
+            );
+            assert_eq!(call_as(accounts.charlie, || loan.stake_keeper(100)), Ok(()));
+            assert!(loan.is_keeper(accounts.charlie));
+            assert_eq!(loan.borrowings_balance(), 9_500);
+
+            // 储备金为 0 时没有奖励，罚没的质押计入储备金后按归还数量的 10% 支付
+            assert_eq!(call_as(accounts.charlie, || loan.liquidate(accounts.bob, collateral_token(), 100)), Ok(()));
+            assert_eq!(mock_erc20::balance_of(base_token(), accounts.charlie), 800);
+            assert_eq!(call_as(accounts.charlie, || loan.slash_keeper(accounts.charlie, 50)), Err(Error::OnlyForGovernance));
+            assert_eq!(call_as(accounts.charlie, || loan.stake_keeper(50)), Ok(()));
+            assert_eq!(loan.slash_keeper(accounts.charlie, 50), Ok(()));
+            assert_eq!(loan.reserves(), 50);
+            assert_eq!(call_as(accounts.charlie, || loan.liquidate(accounts.bob, collateral_token(), 100)), Ok(()));
+            assert_eq!(mock_erc20::balance_of(base_token(), accounts.charlie), 660);
+            assert_eq!(loan.reserves(), 40);
+
+            assert_eq!(call_as(accounts.charlie, || loan.unstake_keeper(101)), Err(Error::InsufficientStake));
+            assert_eq!(call_as(accounts.charlie, || loan.unstake_keeper(100)), Ok(()));
+            assert_eq!(loan.total_keeper_stake(), 0);
+            assert_eq!(
+                call_as(accounts.charlie, || loan.liquidate(accounts.bob, collateral_token(), 100)),
+                Err(Error::NotKeeper)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_position_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(
+                call_as(accounts.bob, || loan.transfer_position(accounts.bob, accounts.charlie)),
+                Err(Error::NoPosition)
+            );
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 400)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.approve_credit(accounts.django, 100)), Ok(()));
+            assert_eq!(call_as(accounts.django, || loan.borrow_with_credit(accounts.bob, collateral_token(), 100)), Ok(()));
+
+            assert_eq!(
+                call_as(accounts.charlie, || loan.transfer_position(accounts.bob, accounts.charlie)),
+                Err(Error::NotPositionOwner)
+            );
+            // 接收者没有同意时不能把仓位转给它
+            assert_eq!(
+                call_as(accounts.bob, || loan.transfer_position(accounts.bob, accounts.eve)),
+                Err(Error::PositionNotAccepted)
+            );
+            assert_eq!(call_as(accounts.eve, || loan.accept_position(Some(accounts.bob))), Ok(()));
+            assert_eq!(loan.position_accepted(accounts.eve), Some(accounts.bob));
+            assert_eq!(call_as(accounts.eve, || loan.accept_position(None)), Ok(()));
+            assert_eq!(loan.position_accepted(accounts.eve), None);
+
+            assert_eq!(call_as(accounts.bob, || loan.approve_position(Some(accounts.charlie))), Ok(()));
+            assert_eq!(loan.position_approved(accounts.bob), Some(accounts.charlie));
+
+            // 接收者已有质押时不能接收
+            fund(collateral_token(), accounts.charlie, 100);
+            assert_eq!(call_as(accounts.charlie, || loan.pledge(collateral_token(), 100)), Ok(()));
+            assert_eq!(
+                call_as(accounts.charlie, || loan.transfer_position(accounts.bob, accounts.charlie)),
+                Err(Error::PositionExists)
+            );
+            assert_eq!(call_as(accounts.charlie, || loan.withdraw_collateral(collateral_token(), 100)), Ok(()));
+            assert_eq!(call_as(accounts.charlie, || loan.transfer_position(accounts.bob, accounts.charlie)), Ok(()));
+
+            // 借款、质押和委托借款的记录都转给了接收者
+            assert_eq!(loan.position_approved(accounts.bob), None);
+            assert_eq!(loan.borrowing_of(accounts.bob), 0);
+            assert_eq!(loan.pledge_of(accounts.bob, collateral_token()), 0);
+            assert_eq!(loan.borrowing_of(accounts.charlie), 500);
+            assert_eq!(loan.pledge_of(accounts.charlie, collateral_token()), 1_000);
+            assert_eq!(loan.delegated_debt_of(accounts.bob, accounts.django), 0);
+            assert_eq!(loan.delegated_debt_of(accounts.charlie, accounts.django), 100);
+            assert_eq!(loan.borrowers(0, 10), vec![accounts.charlie]);
+            assert_eq!(
+                call_as(accounts.bob, || loan.withdraw_collateral(collateral_token(), 1)),
+                Err(Error::InsufficientPledge)
+            );
+
+            // 接收者已有借款时即使同意了也不能接收
+            fund(collateral_token(), accounts.bob, 100);
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 100)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 10)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.accept_position(Some(accounts.charlie))), Ok(()));
+            assert_eq!(
+                call_as(accounts.charlie, || loan.transfer_position(accounts.charlie, accounts.bob)),
+                Err(Error::PositionExists)
+            );
+        }
+
+        #[ink::test]
+        fn isolation_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            let isolated_token = AccountId::from([0x30; 32]);
+            mock_price::set_price(isolated_token, PRICE_PRECISION);
+            let config = CollateralConfig {
+                collateral_factor_bps: 5000,
+                liquidation_threshold_bps: 8000,
+                liquidation_bonus_bps: 500,
+            };
+            assert_eq!(loan.set_collateral_config(isolated_token, config), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.set_isolation(isolated_token, Some(300))), Err(Error::OnlyForGovernance));
+            assert_eq!(loan.set_isolation(isolated_token, Some(300)), Ok(()));
+            fund(isolated_token, accounts.bob, 1_000);
+
+            // 已经质押了普通币种时不能再质押隔离币种
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 100)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.pledge(isolated_token, 100)), Err(Error::IsolationViolation));
+            assert_eq!(call_as(accounts.bob, || loan.swap_collateral(collateral_token(), isolated_token, 100)), Ok(()));
+            assert_eq!(loan.isolation_asset_of(accounts.bob), Some(isolated_token));
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 100)), Err(Error::IsolationViolation));
+
+            // 隔离模式下的借款不能超过上限
+            assert_eq!(call_as(accounts.bob, || loan.pledge(isolated_token, 900)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(isolated_token, 200)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(isolated_token, 101)), Err(Error::CapReached));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(isolated_token, 100)), Ok(()));
+            assert_eq!(loan.isolated_total(isolated_token), 300);
+
+            assert_eq!(call_as(accounts.bob, || loan.repay(100)), Ok(()));
+            assert_eq!(loan.isolated_total(isolated_token), 200);
+            fund(base_token(), accounts.bob, 100);
+            assert_eq!(call_as(accounts.bob, || loan.repay_all()), Ok(()));
+            assert_eq!(loan.isolated_total(isolated_token), 0);
+        }
+
+        #[ink::test]
+        fn rewards_work() {
+            let mut loan = setup();
+            let accounts = accounts();
+            let reward_token = AccountId::from([0x40; 32]);
+            assert_eq!(loan.claim_rewards(), Err(Error::RewardsDisabled));
+            assert_eq!(call_as(accounts.bob, || loan.set_reward_speeds(10, 10)), Err(Error::OnlyForOwner));
+            assert_eq!(loan.set_reward_token(Some(reward_token)), Ok(()));
+            assert_eq!(loan.set_reward_speeds(10, 20), Ok(()));
+            fund(reward_token, accounts.alice, 1_000);
+            assert_eq!(call_as(accounts.bob, || loan.fund_rewards(1_000)), Err(Error::OnlyForOwner));
+            assert_eq!(loan.fund_rewards(1_000), Ok(()));
+            assert_eq!(loan.reward_balance(), 1_000);
+
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 500)), Ok(()));
+            advance_blocks(10);
+            assert_eq!(loan.rewards_of(accounts.alice), 100);
+            assert_eq!(loan.rewards_of(accounts.bob), 200);
+
+            // Alice 把一半份额转给 Charlie 后两人平分存款奖励
+            assert_eq!(loan.transfer_shares(accounts.charlie, 5_000), Ok(()));
+            advance_blocks(10);
+            assert_eq!(loan.rewards_of(accounts.alice), 150);
+            assert_eq!(loan.rewards_of(accounts.charlie), 50);
+
+            assert_eq!(call_as(accounts.bob, || loan.claim_rewards()), Ok(400));
+            assert_eq!(mock_erc20::balance_of(reward_token, accounts.bob), 400);
+            assert_eq!(loan.rewards_of(accounts.bob), 0);
+            assert_eq!(loan.claim_rewards(), Ok(150));
+            assert_eq!(loan.reward_balance(), 450);
+        }
+
+        #[ink::test]
+        fn reward_budget_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            let reward_token = AccountId::from([0x40; 32]);
+            // 基础代币不能作为奖励代币，否则领取奖励会转出存款人的资金
+            assert_eq!(loan.set_reward_token(Some(base_token())), Err(Error::InvalidRewardToken));
+            assert_eq!(loan.fund_rewards(100), Err(Error::RewardsDisabled));
+            assert_eq!(loan.set_reward_token(Some(reward_token)), Ok(()));
+            assert_eq!(loan.set_reward_speeds(10, 0), Ok(()));
+            advance_blocks(10);
+
+            // 没有奖励余额时不能领取，已经结算的奖励保留到补充之后
+            assert_eq!(loan.claim_rewards(), Err(Error::InsufficientRewards));
+            assert_eq!(loan.rewards_of(accounts.alice), 100);
+
+            // 余额不足时只领取余额
+            fund(reward_token, accounts.alice, 1_000);
+            assert_eq!(loan.fund_rewards(60), Ok(()));
+            assert_eq!(loan.set_reward_token(Some(AccountId::from([0x41; 32]))), Err(Error::InvalidRewardToken));
+            assert_eq!(loan.claim_rewards(), Ok(60));
+            assert_eq!(loan.reward_balance(), 0);
+            assert_eq!(loan.rewards_of(accounts.alice), 40);
+            assert_eq!(loan.claim_rewards(), Err(Error::InsufficientRewards));
+            assert_eq!(loan.fund_rewards(100), Ok(()));
+            assert_eq!(loan.claim_rewards(), Ok(40));
+            assert_eq!(mock_erc20::balance_of(reward_token, accounts.alice), 1_000 - 160 + 100);
+        }
+
+        #[ink::test]
+        fn reward_speed_change_with_borrows_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(loan.update_config(fixed_rate_config()), Ok(()));
+            let reward_token = AccountId::from([0x40; 32]);
+            assert_eq!(loan.set_reward_token(Some(reward_token)), Ok(()));
+            assert_eq!(loan.set_reward_speeds(0, 20), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 400)), Ok(()));
+            advance_blocks(10);
+            assert_eq!(loan.rewards_of(accounts.bob), 200);
+
+            // 借款还没有结算利息时修改速度，之前的区块按原来的速度累积
+            assert_eq!(loan.set_reward_speeds(0, 40), Ok(()));
+            advance_blocks(10);
+            assert_eq!(loan.rewards_of(accounts.bob), 600);
+
+            // Charlie 借款后两人按折算的本金分配，已经累积的利息不会让分出的奖励少于速度
+            fund(collateral_token(), accounts.charlie, 1_000);
+            assert_eq!(call_as(accounts.charlie, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.charlie, || loan.borrow(collateral_token(), 400)), Ok(()));
+            advance_blocks(10);
+            let emitted = loan.rewards_of(accounts.bob) + loan.rewards_of(accounts.charlie);
+            assert!((999..=1_000).contains(&emitted));
+        }
+
+        #[ink::test]
+        fn grace_period_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            let config = Config { grace_blocks: 5, ..Config::default() };
+            assert_eq!(loan.update_config(config), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 500)), Ok(()));
+            fund(base_token(), accounts.charlie, 1_000);
+            assert_eq!(loan.flag_at_risk(accounts.bob), Err(Error::PositionHealthy));
+
+            // 价格下跌后开始计时，宽限期内不能清算
+            mock_price::set_price(collateral_token(), PRICE_PRECISION / 2);
+            assert_eq!(loan.flag_at_risk(accounts.bob), Ok(()));
+            assert_eq!(loan.at_risk_since(accounts.bob), Some(0));
+            assert_eq!(loan.flag_at_risk(accounts.bob), Err(Error::GracePeriodActive));
+            assert_eq!(
+                call_as(accounts.charlie, || loan.liquidate(accounts.bob, collateral_token(), 100)),
+                Err(Error::GracePeriodActive)
+            );
+
+            // 价格恢复后清算时发现仓位健康，停止计时
+            advance_blocks(5);
+            mock_price::set_price(collateral_token(), PRICE_PRECISION);
+            assert_eq!(
+                call_as(accounts.charlie, || loan.liquidate(accounts.bob, collateral_token(), 100)),
+                Err(Error::PositionHealthy)
+            );
+            assert_eq!(loan.at_risk_since(accounts.bob), None);
+
+            // 再次下跌时需要重新开始计时，不能沿用之前的计时立即清算
+            mock_price::set_price(collateral_token(), PRICE_PRECISION / 2);
+            assert_eq!(
+                call_as(accounts.charlie, || loan.liquidate(accounts.bob, collateral_token(), 100)),
+                Err(Error::GracePeriodActive)
+            );
+            assert_eq!(loan.flag_at_risk(accounts.bob), Ok(()));
+            assert_eq!(loan.at_risk_since(accounts.bob), Some(5));
+
+            // flag_at_risk 发现仓位健康时同样停止计时
+            mock_price::set_price(collateral_token(), PRICE_PRECISION);
+            assert_eq!(loan.flag_at_risk(accounts.bob), Err(Error::PositionHealthy));
+            assert_eq!(loan.at_risk_since(accounts.bob), None);
+        }
+
+        #[ink::test]
+        fn bad_debt_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 500)), Ok(()));
+            fund(base_token(), accounts.charlie, 1_000);
+
+            // 价格下跌到 0.21，全部质押只值 210，清算后剩余借款成为坏账
+            mock_price::set_price(collateral_token(), PRICE_PRECISION * 21 / 100);
+            assert_eq!(call_as(accounts.charlie, || loan.liquidate(accounts.bob, collateral_token(), 500)), Ok(()));
+            assert_eq!(loan.pledge_of(accounts.bob, collateral_token()), 0);
+            assert_eq!(loan.borrowing_of(accounts.bob), 300);
+            assert_eq!(loan.bad_debt(accounts.bob), 300);
+            assert_eq!(loan.total_bad_debt(), 300);
+
+            // 核销后损失由存款人分摊
+            assert_eq!(call_as(accounts.bob, || loan.write_off(accounts.bob)), Err(Error::OnlyForOwner));
+            assert_eq!(loan.write_off(accounts.bob), Ok(()));
+            assert_eq!(loan.borrowing_of(accounts.bob), 0);
+            assert_eq!(loan.total_bad_debt(), 0);
+            assert_eq!(loan.supply_balance_of(accounts.alice), 9_700);
+        }
+
+        #[ink::test]
+        fn tier_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            let config = Config { reserve_factor_bps: 10_000, ..fixed_rate_config() };
+            assert_eq!(loan.update_config(config), Ok(()));
+            let config = TierConfig { max_borrow: 300, rate_discount_bps: 5_000 };
+            assert_eq!(loan.set_tier_config(Tier::Bronze, config), Ok(()));
+            assert_eq!(loan.set_tier(accounts.bob, Some(Tier::Bronze)), Ok(()));
+            assert_eq!(loan.tier_of(accounts.bob), Some(Tier::Bronze));
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+
+            // 质押足够但超过了等级的借款上限
+            assert_eq!(
+                call_as(accounts.bob, || loan.borrow(collateral_token(), 400)),
+                Err(Error::TierLimitExceeded)
+            );
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 200)), Ok(()));
+
+            // 利息打五折，10 个区块的利息从 2 减少到 1
+            advance_blocks(10);
+            assert_eq!(loan.current_debt(accounts.bob), 201);
+            assert_eq!(call_as(accounts.bob, || loan.repay(1)), Ok(()));
+            assert_eq!(loan.borrowing_of(accounts.bob), 200);
+            assert_eq!(loan.total_borrowings(), 200);
+            // 储备金只计入实际收取的利息
+            assert_eq!(loan.reserves(), 1);
+        }
+
+        #[ink::test]
+        fn withdraw_excess_collateral_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 200)), Ok(()));
+
+            // 健康系数 2 时需要保留价值 200 × 2 / 80% = 500 的质押
+            assert_eq!(
+                call_as(accounts.bob, || loan.withdraw_excess_collateral(collateral_token(), INDEX_PRECISION * 2)),
+                Ok(500)
+            );
+            assert_eq!(loan.pledge_of(accounts.bob, collateral_token()), 500);
+            assert_eq!(loan.health_factor(accounts.bob), Ok(INDEX_PRECISION * 2));
+
+            // 目标健康系数很低时仍需满足 50% 的借款比例，最少保留 400
+            assert_eq!(
+                call_as(accounts.bob, || loan.withdraw_excess_collateral(collateral_token(), INDEX_PRECISION)),
+                Ok(100)
+            );
+            assert_eq!(
+                call_as(accounts.bob, || loan.withdraw_excess_collateral(collateral_token(), INDEX_PRECISION)),
+                Err(Error::WouldBeUndercollateralized)
+            );
+        }
+
+        #[ink::test]
+        fn auction_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 500)), Ok(()));
+            fund(base_token(), accounts.charlie, 1_000);
+            assert_eq!(
+                call_as(accounts.charlie, || loan.start_auction(accounts.bob, collateral_token())),
+                Err(Error::AuctionDisabled)
+            );
+
+            // 拍卖 10 个区块，价格从当前价格的 110% 下降到 90%
+            let config = Config {
+                auction_blocks: 10,
+                auction_start_bps: 11_000,
+                auction_end_bps: 9_000,
+                ..Config::default()
+            };
+            assert_eq!(loan.update_config(config), Ok(()));
+            mock_price::set_price(collateral_token(), PRICE_PRECISION / 2);
+            assert_eq!(
+                call_as(accounts.charlie, || loan.liquidate(accounts.bob, collateral_token(), 100)),
+                Err(Error::AuctionModeActive)
+            );
+            assert_eq!(call_as(accounts.charlie, || loan.start_auction(accounts.bob, collateral_token())), Ok(()));
+            assert_eq!(loan.pledge_of(accounts.bob, collateral_token()), 0);
+            assert_eq!(loan.auction_price(accounts.bob, collateral_token()), Some(PRICE_PRECISION * 55 / 100));
+
+            // 5 个区块后价格为 0.5，买入 400 个质押支付 200
+            advance_blocks(5);
+            assert_eq!(loan.auction_price(accounts.bob, collateral_token()), Some(PRICE_PRECISION / 2));
+            assert_eq!(call_as(accounts.charlie, || loan.bid(accounts.bob, collateral_token(), 400)), Ok(()));
+            assert_eq!(loan.borrowing_of(accounts.bob), 300);
+            assert_eq!(mock_erc20::balance_of(collateral_token(), accounts.charlie), 400);
+
+            // 价格降到最低的 0.45 后买入全部剩余的 600 个质押，支付 270 后仍有 30 的借款成为坏账
+            advance_blocks(10);
+            assert_eq!(call_as(accounts.charlie, || loan.bid(accounts.bob, collateral_token(), 1_000)), Ok(()));
+            assert_eq!(mock_erc20::balance_of(base_token(), accounts.charlie), 530);
+            assert_eq!(loan.borrowing_of(accounts.bob), 30);
+            assert_eq!(loan.auction_of(accounts.bob, collateral_token()), None);
+            assert_eq!(loan.bad_debt(accounts.bob), 30);
+        }
+
+        #[ink::test]
+        fn config_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(loan.config(), Config::default());
+            assert_eq!(loan.governance(), accounts.alice);
+
+            // 参数需要合法
+            let invalid = Config { reserve_factor_bps: 10_001, ..Config::default() };
+            assert_eq!(loan.update_config(invalid), Err(Error::InvalidRatio));
+
+            // 借款比例必须小于清算线
+            let risk = CollateralConfig {
+                collateral_factor_bps: 8000,
+                liquidation_threshold_bps: 8000,
+                liquidation_bonus_bps: 500,
+            };
+            assert_eq!(loan.set_collateral_config(collateral_token(), risk), Err(Error::InvalidRatio));
+
+            // 治理权交给 Bob 后只有 Bob 可以修改
+            assert_eq!(loan.set_governance(accounts.bob), Ok(()));
+            assert_eq!(loan.update_config(fixed_rate_config()), Err(Error::OnlyForGovernance));
+            assert_eq!(call_as(accounts.bob, || loan.update_config(fixed_rate_config())), Ok(()));
+            assert_eq!(loan.config().base_rate, INDEX_PRECISION / 1_000);
+
+            // 借款上限在 config 中
+            let capped = Config { borrow_cap: Some(100), ..Config::default() };
+            assert_eq!(call_as(accounts.bob, || loan.update_config(capped)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+            assert_eq!(
+                call_as(accounts.bob, || loan.borrow(collateral_token(), 101)),
+                Err(Error::CapReached)
+            );
+        }
+
+        #[ink::test]
+        fn pause_works() {
+            let mut loan = setup();
+            let accounts = accounts();
+            assert_eq!(call_as(accounts.bob, || loan.pledge(collateral_token(), 1_000)), Ok(()));
+
+            assert_eq!(
+                call_as(accounts.bob, || loan.set_paused(PauseAction::Borrow, true)),
+                Err(Error::OnlyForGuardian)
+            );
+            assert_eq!(loan.set_guardian(Some(accounts.charlie)), Ok(()));
+            assert_eq!(call_as(accounts.charlie, || loan.set_paused(PauseAction::Borrow, true)), Ok(()));
+            assert_eq!(
+                call_as(accounts.bob, || loan.borrow(collateral_token(), 100)),
+                Err(Error::Paused)
+            );
+            assert_eq!(call_as(accounts.charlie, || loan.set_paused(PauseAction::Borrow, false)), Ok(()));
+            assert_eq!(call_as(accounts.bob, || loan.borrow(collateral_token(), 100)), Ok(()));
+
+            // 守护者不能修改经济参数
+            assert_eq!(
+                call_as(accounts.charlie, || loan.update_config(Config::default())),
+                Err(Error::OnlyForGovernance)
+            );
         }
     }
 }
@@ -19,12 +19,112 @@ pub trait FetchRandom {
 
     #[ink(extension = 1103, returns_result = false)]
     fn create_kitty() -> u32;
+
+    /// Hands a registered claim over to `to`; the runtime side checks that the claim exists
+    /// and is owned by the calling contract.
+    #[ink(extension = 1104, returns_result = false)]
+    fn transfer_claim(claim: Vec<u8>, to: ink_env::AccountId);
+
+    /// Reads the owner of a claim and the block it was registered in, `None` if it doesn't exist.
+    #[ink(extension = 1105, returns_result = false)]
+    fn claim_owner(claim: Vec<u8>) -> Option<(ink_env::AccountId, <ink_env::DefaultEnvironment as Environment>::BlockNumber)>;
+
+    /// Breeds a new kitty from two parents owned by the calling contract and returns the child id.
+    #[ink(extension = 1106, returns_result = false)]
+    fn breed_kitty(parent1: u32, parent2: u32) -> u32;
+
+    /// Transfers a kitty to `to`; the kitties pallet checks that the calling contract owns it.
+    #[ink(extension = 1107, returns_result = false)]
+    fn transfer_kitty(kitty_id: u32, to: ink_env::AccountId);
+
+    /// Reads the DNA of a kitty.
+    #[ink(extension = 1108, returns_result = false)]
+    fn kitty_dna(kitty_id: u32) -> [u8; 16];
+
+    /// Reads the owner of a kitty, `None` if it doesn't exist.
+    #[ink(extension = 1109, returns_result = false)]
+    fn kitty_owner(kitty_id: u32) -> Option<ink_env::AccountId>;
+
+    /// Like `fetch_random` but mixes `subject` into the random source, so callers using
+    /// different subjects in the same block get independent values.
+    #[ink(extension = 1110, returns_result = false)]
+    fn fetch_random_seeded(subject: Vec<u8>) -> [u8; 32];
+
+    /// VRF-style randomness: returns the output for `subject` together with the proof (64 bytes for sr25519)
+    /// produced by the runtime's VRF key, so consumers can check the output against the public key.
+    #[ink(extension = 1111, returns_result = false)]
+    fn fetch_random_vrf(subject: Vec<u8>) -> ([u8; 32], Vec<u8>);
+
+    /// Verifies an sr25519 `signature` of `message` by `pubkey` with the runtime's host crypto.
+    #[ink(extension = 1112, returns_result = false)]
+    fn verify_sr25519(pubkey: [u8; 32], message: Vec<u8>, signature: [u8; 64]) -> bool;
+
+    /// Verifies a 65 byte recoverable ecdsa `signature` of the blake2-256 hash of `message`
+    /// against a 33 byte compressed `pubkey`.
+    #[ink(extension = 1113, returns_result = false)]
+    fn verify_ecdsa(pubkey: Vec<u8>, message: Vec<u8>, signature: Vec<u8>) -> bool;
+
+    /// Reads the raw SCALE encoded value under a runtime storage `key`, `None` if it is empty.
+    /// The runtime side only serves keys on its safelist and fails for any other key.
+    #[ink(extension = 1114, returns_result = false)]
+    fn read_storage(key: Vec<u8>) -> Option<Vec<u8>>;
+
+    /// Reads the current staking era index, `None` before the first era starts.
+    #[ink(extension = 1115, returns_result = false)]
+    fn current_era() -> Option<u32>;
+
+    /// Reads the ideal number of validators set in the staking pallet.
+    #[ink(extension = 1116, returns_result = false)]
+    fn validator_count() -> u32;
+
+    /// Reads the active bonded balance of a stash account, 0 if it isn't bonded.
+    #[ink(extension = 1117, returns_result = false)]
+    fn staked_of(account: ink_env::AccountId) -> <ink_env::DefaultEnvironment as Environment>::Balance;
+
+    /// Dispatches `system::remark_with_event` with the contract as origin, anchoring `data` in a runtime event.
+    #[ink(extension = 1118, returns_result = false)]
+    fn dispatch_remark(data: Vec<u8>);
+
+    /// Dispatches a SCALE encoded runtime call with the contract as origin,
+    /// the runtime side rejects calls that are not on its safelist.
+    #[ink(extension = 1119, returns_result = false)]
+    fn dispatch_call(call: Vec<u8>);
+
+    /// Reads the oracle price of `asset` and the timestamp it was last updated at,
+    /// fails with `PriceUnavailable` when the runtime has no price for the asset.
+    #[ink(extension = 1120, returns_result = false)]
+    fn fetch_price(asset: u32) -> (u128, <ink_env::DefaultEnvironment as Environment>::Timestamp);
+
+    /// Reads the ids of all kitties owned by `owner` in the kitties pallet.
+    #[ink(extension = 1121, returns_result = false)]
+    fn kitties_of(owner: ink_env::AccountId) -> Vec<u32>;
+
+    /// Removes a claim owned by the calling contract.
+    #[ink(extension = 1122, returns_result = false)]
+    fn revoke_claim(claim: Vec<u8>);
 }
 
+/// Errors reported by the runtime side of the extension, status code `n` maps to the `n`-th variant
 #[derive(Debug, Copy, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 pub enum RandomReadErr {
     FailGetRandomSource,
+    /// `create_claim` was called with a claim that is already registered
+    ClaimAlreadyExists,
+    /// The claim is not registered
+    ClaimNotFound,
+    /// The claim is owned by another account
+    NotClaimOwner,
+    /// The kitties pallet failed to create a kitty, e.g. the kitty count overflowed
+    KittyCreationFailed,
+    /// The kitty doesn't exist
+    KittyNotFound,
+    /// The kitty is owned by another account
+    KittyNotOwned,
+    /// The two parents can't be bred, e.g. they are the same kitty
+    KittyBreedingFailed,
+    /// The oracle has no price for the asset
+    PriceUnavailable,
 }
 
 impl ink_env::chain_extension::FromStatusCode for RandomReadErr {
@@ -32,6 +132,14 @@ impl ink_env::chain_extension::FromStatusCode for RandomReadErr {
         match status_code {
             0 => Ok(()),
             1 => Err(Self::FailGetRandomSource),
+            2 => Err(Self::ClaimAlreadyExists),
+            3 => Err(Self::ClaimNotFound),
+            4 => Err(Self::NotClaimOwner),
+            5 => Err(Self::KittyCreationFailed),
+            6 => Err(Self::KittyNotFound),
+            7 => Err(Self::KittyNotOwned),
+            8 => Err(Self::KittyBreedingFailed),
+            9 => Err(Self::PriceUnavailable),
             _ => panic!("encountered unknown status code"),
         }
     }
@@ -59,6 +167,7 @@ impl Environment for CustomEnvironment {
 mod randkey {
     use super::RandomReadErr;
     use crate::{Vec, format};
+    use ink_storage::collections::HashMap as StorageHashMap;
 
     /// Defines the storage of your contract.
     /// Add new fields to the below struct in order
@@ -68,6 +177,144 @@ mod randkey {
         /// Stores a single `bool` value on the storage.
         value: [u8; 32],
         kitty_id: u32,
+        /// Kitties listed for sale, kitty id -> (seller, price)
+        kitty_prices: StorageHashMap<u32, (AccountId, Balance)>,
+        /// Values produced by the last `update_many`, at most `MAX_BATCH` of them
+        batch: Vec<[u8; 32]>,
+        /// Number of `update` calls, mixed into the subject so each call gets its own value
+        nonce: u64,
+        /// Ring buffer of the last `HISTORY_SIZE` random values and the blocks they were produced in,
+        /// the draw with index `i` is stored in slot `i % HISTORY_SIZE`
+        history: StorageHashMap<u32, ([u8; 32], BlockNumber)>,
+        /// Number of draws recorded so far
+        draw_count: u32,
+        /// Account that deployed the contract and manages the games
+        owner: AccountId,
+        /// Price of one lottery entry, 0 disables the lottery
+        lottery_ticket_price: Balance,
+        /// Number of blocks a lottery round stays open after its first entry
+        lottery_round_blocks: BlockNumber,
+        /// Current lottery round
+        lottery_round: u32,
+        /// Block after which anyone can draw the current round, `None` until the first entry
+        lottery_ends_at: Option<BlockNumber>,
+        /// Balance collected from the entries of the current round
+        lottery_pot: Balance,
+        /// Entrants of every round, (round, index) -> account, kept after the draw since the round
+        /// in the key already separates them from later rounds and clearing would cost gas per entry
+        lottery_entrants: StorageHashMap<(u32, u32), AccountId>,
+        /// Number of entries in the current round
+        lottery_entrant_count: u32,
+        /// Balance held for paying out dice wins, separate from the lottery pot
+        dice_bankroll: Balance,
+        /// Share of the fair 6x payout kept by the house, in basis points
+        dice_house_edge_bps: u32,
+        /// Sum of all dice wagers and of all dice payouts, their difference is the house profit
+        dice_total_wagered: Balance,
+        dice_total_paid: Balance,
+        /// Subject, output and proof of the last `update_vrf`
+        vrf_subject: Vec<u8>,
+        vrf_output: [u8; 32],
+        vrf_proof: Vec<u8>,
+        /// Kitties created, bred or transferred through this contract, owner -> kitty ids,
+        /// kitties moved directly in the pallet are not reflected here
+        owned_kitties: StorageHashMap<AccountId, Vec<u32>>,
+        /// Last price fetched by `update_price`, asset -> (price, timestamp)
+        prices: StorageHashMap<u32, (u128, Timestamp)>,
+        /// Accounts that registered claims through this contract, the runtime records the contract
+        /// itself as the owner so the messages check the caller against this map
+        claim_owners: StorageHashMap<Vec<u8>, AccountId>,
+        /// Accounts owning the kitties created or bred through this contract, which the kitties pallet
+        /// records as owned by the contract itself, kitty id -> owner
+        kitty_owners: StorageHashMap<u32, AccountId>,
+        /// Part of the dice bankroll reserved for the payouts of pending bets
+        dice_reserved: Balance,
+        /// Pending dice bets, player -> (guess, wager, payout on a win, block the bet settles in)
+        dice_bets: StorageHashMap<AccountId, (u8, Balance, Balance, BlockNumber)>,
+        /// Random seed fetched by the first dice call of a block, block -> seed, bets settling
+        /// in that block are rolled with it no matter when they are settled
+        dice_seeds: StorageHashMap<BlockNumber, [u8; 32]>,
+    }
+
+    /// Maximum number of random values `update_many` can produce in one call
+    const MAX_BATCH: u8 = 32;
+
+    /// Number of past random values kept in the history
+    const HISTORY_SIZE: u32 = 64;
+
+    /// Number of faces of the dice
+    const DICE_FACES: u8 = 6;
+
+    /// Number of blocks after its settlement block within which a dice bet can still be rolled,
+    /// a bet that isn't rolled by then is refunded
+    const DICE_SETTLE_WINDOW: BlockNumber = 20;
+
+    /// Denominator of ratios given in basis points
+    const BPS_DENOMINATOR: Balance = 10_000;
+
+    /// Storage key of `Balances::TotalIssuance`, twox128("Balances") ++ twox128("TotalIssuance")
+    const TOTAL_ISSUANCE_KEY: [u8; 32] = [
+        0xc2, 0x26, 0x12, 0x76, 0xcc, 0x9d, 0x1f, 0x85, 0x98, 0xea, 0x4b, 0x6a, 0x74, 0xb1, 0x5c, 0x2f,
+        0x57, 0xc8, 0x75, 0xe4, 0xcf, 0xf7, 0x41, 0x48, 0xe4, 0x62, 0x8f, 0x26, 0x4b, 0x97, 0x4c, 0x80,
+    ];
+
+    /// Storage key of `Timestamp::Now`, twox128("Timestamp") ++ twox128("Now")
+    const TIMESTAMP_NOW_KEY: [u8; 32] = [
+        0xf0, 0xc3, 0x65, 0xc3, 0xcf, 0x59, 0xd6, 0x71, 0xeb, 0x72, 0xda, 0x0e, 0x7a, 0x41, 0x13, 0xc4,
+        0x9f, 0x1f, 0x05, 0x15, 0xf4, 0x62, 0xcd, 0xcf, 0x84, 0xe0, 0xf1, 0xd6, 0x04, 0x5d, 0xfc, 0xbb,
+    ];
+
+    /// Errors of the contract side logic, extension failures are wrapped in `Extension`
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        Extension(RandomReadErr),
+        /// The caller doesn't own the kitty
+        NotKittyOwner,
+        /// The kitty isn't listed for sale
+        KittyNotForSale,
+        /// The transferred balance is lower than the kitty price
+        InsufficientPayment,
+        /// Forwarding the payment to the seller failed
+        PaymentFailed,
+        /// `min` is greater than `max`
+        InvalidRange,
+        /// The batch size is 0 or larger than `MAX_BATCH`
+        InvalidBatchSize,
+        /// Only the owner can call this message
+        NotOwner,
+        /// The lottery ticket price is 0
+        LotteryDisabled,
+        /// The transferred balance doesn't match the ticket price
+        InvalidTicketPayment,
+        /// The round is still open and only the owner can draw it early
+        LotteryNotOver,
+        /// Nobody entered the current round
+        NoEntrants,
+        /// The dice guess must be between 1 and 6
+        InvalidGuess,
+        /// The wager is 0
+        ZeroWager,
+        /// The bankroll can't cover the payout of this wager
+        InsufficientBankroll,
+        /// The house edge can't exceed 10000 basis points
+        InvalidHouseEdge,
+        /// A runtime storage value couldn't be decoded as the expected type
+        InvalidStorageValue,
+        /// The caller didn't register the claim through this contract
+        NotClaimOwner,
+        /// The player already has a dice bet waiting to be settled
+        BetPending,
+        /// The player has no dice bet waiting to be settled
+        NoPendingBet,
+        /// The dice bet can't be settled before its settlement block
+        BetNotReady,
+    }
+
+    impl From<RandomReadErr> for Error {
+        fn from(error: RandomReadErr) -> Self {
+            Error::Extension(error)
+        }
     }
     #[ink(event)]
     pub struct RandomUpdated{
@@ -75,21 +322,173 @@ mod randkey {
         new: [u8; 32],
     }
     #[ink(event)]
+    pub struct VrfRandomUpdated{
+        #[ink(topic)]
+        output: [u8; 32],
+        subject: Vec<u8>,
+        proof: Vec<u8>,
+    }
+    #[ink(event)]
+    pub struct RandomBatchUpdated{
+        #[ink(topic)]
+        seed: [u8; 32],
+        count: u8,
+    }
+    #[ink(event)]
+    pub struct LotteryEntered{
+        #[ink(topic)]
+        round: u32,
+        #[ink(topic)]
+        account: AccountId,
+    }
+    #[ink(event)]
+    pub struct WinnerSelected{
+        #[ink(topic)]
+        round: u32,
+        #[ink(topic)]
+        winner: AccountId,
+        prize: Balance,
+    }
+    #[ink(event)]
+    pub struct DiceBetPlaced{
+        #[ink(topic)]
+        player: AccountId,
+        guess: u8,
+        wager: Balance,
+        settle_at: BlockNumber,
+    }
+    #[ink(event)]
+    pub struct DiceRolled{
+        #[ink(topic)]
+        player: AccountId,
+        guess: u8,
+        result: u8,
+        wager: Balance,
+        payout: Balance,
+    }
+    #[ink(event)]
+    pub struct DiceRefunded{
+        #[ink(topic)]
+        player: AccountId,
+        wager: Balance,
+    }
+    #[ink(event)]
+    pub struct RemarkDispatched{
+        #[ink(topic)]
+        caller: AccountId,
+        #[ink(topic)]
+        data_hash: [u8; 32],
+    }
+    #[ink(event)]
+    pub struct CallDispatched{
+        #[ink(topic)]
+        call_hash: [u8; 32],
+    }
+    #[ink(event)]
     pub struct ClaimCreated{
         #[ink(topic)]
         claim: Vec<u8>,
     }
     #[ink(event)]
+    pub struct ClaimRevoked{
+        #[ink(topic)]
+        claim: Vec<u8>,
+        #[ink(topic)]
+        owner: AccountId,
+    }
+    #[ink(event)]
+    pub struct PriceUpdated{
+        #[ink(topic)]
+        asset: u32,
+        price: u128,
+        timestamp: Timestamp,
+    }
+    #[ink(event)]
+    pub struct ClaimTransferred{
+        #[ink(topic)]
+        claim: Vec<u8>,
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+    }
+    #[ink(event)]
     pub struct KittyCreated{
         #[ink(topic)]
         kitty_id: u32,
     }
+    #[ink(event)]
+    pub struct KittyBred{
+        #[ink(topic)]
+        kitty_id: u32,
+        #[ink(topic)]
+        parent1: u32,
+        #[ink(topic)]
+        parent2: u32,
+    }
+    #[ink(event)]
+    pub struct KittyPriceSet{
+        #[ink(topic)]
+        kitty_id: u32,
+        #[ink(topic)]
+        seller: AccountId,
+        price: Option<Balance>,
+    }
+    #[ink(event)]
+    pub struct KittySold{
+        #[ink(topic)]
+        kitty_id: u32,
+        #[ink(topic)]
+        seller: AccountId,
+        #[ink(topic)]
+        buyer: AccountId,
+        price: Balance,
+    }
+    #[ink(event)]
+    pub struct KittyTransferred{
+        #[ink(topic)]
+        kitty_id: u32,
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+    }
 
     impl Randkey {
         /// Constructor that initializes the `bool` value to the given `init_value`.
         #[ink(constructor)]
         pub fn new(init_value: [u8; 32]) -> Self {
-            Self { value: init_value, kitty_id: Default::default() }
+            Self {
+                value: init_value,
+                kitty_id: Default::default(),
+                kitty_prices: StorageHashMap::new(),
+                batch: Vec::new(),
+                nonce: 0,
+                history: StorageHashMap::new(),
+                draw_count: 0,
+                owner: Self::env().caller(),
+                lottery_ticket_price: 0,
+                lottery_round_blocks: 0,
+                lottery_round: 0,
+                lottery_ends_at: None,
+                lottery_pot: 0,
+                lottery_entrants: StorageHashMap::new(),
+                lottery_entrant_count: 0,
+                dice_bankroll: 0,
+                dice_house_edge_bps: 0,
+                dice_total_wagered: 0,
+                dice_total_paid: 0,
+                vrf_subject: Vec::new(),
+                vrf_output: [0; 32],
+                vrf_proof: Vec::new(),
+                owned_kitties: StorageHashMap::new(),
+                prices: StorageHashMap::new(),
+                claim_owners: StorageHashMap::new(),
+                kitty_owners: StorageHashMap::new(),
+                dice_reserved: 0,
+                dice_bets: StorageHashMap::new(),
+                dice_seeds: StorageHashMap::new(),
+            }
         }
 
         /// Constructor that initializes the `bool` value to `false`.
@@ -105,8 +504,10 @@ mod randkey {
         /// to `false` and vice versa.
         #[ink(message)]
         pub fn update(&mut self) -> Result<(), RandomReadErr> {
-            let new_randomkey = self.env().extension().fetch_random()?;
+            let subject = self.next_subject();
+            let new_randomkey = self.seeded_random(subject)?;
             self.value = new_randomkey;
+            self.record_draw(new_randomkey);
 
             let message = format!("randdomkey =  {:?}", new_randomkey);
             ink_env::debug_println(&message);
@@ -116,15 +517,134 @@ mod randkey {
             Ok(())
         }
 
+        /// Fetch a VRF output and its proof for the next per-call subject and store all three,
+        /// anyone can then verify the output off-chain with the runtime's VRF public key
+        #[ink(message)]
+        pub fn update_vrf(&mut self) -> Result<(), RandomReadErr> {
+            let subject = self.next_subject();
+            let (output, proof) = self.env().extension().fetch_random_vrf(subject.clone())?;
+            self.value = output;
+            self.record_draw(output);
+            self.vrf_subject = subject.clone();
+            self.vrf_output = output;
+            self.vrf_proof = proof.clone();
+
+            self.env().emit_event(VrfRandomUpdated{ output, subject, proof });
+            Ok(())
+        }
+
+        /// Returns the subject, output and proof of the last `update_vrf`
+        #[ink(message)]
+        pub fn get_vrf(&self) -> (Vec<u8>, [u8; 32], Vec<u8>) {
+            (self.vrf_subject.clone(), self.vrf_output, self.vrf_proof.clone())
+        }
+
+        /// Fetch one seed and derive `n` random values from it, the values are stored
+        /// in place of the previous batch and returned
+        #[ink(message)]
+        pub fn update_many(&mut self, n: u8) -> Result<Vec<[u8; 32]>, Error> {
+            if n == 0 || n > MAX_BATCH {
+                return Err(Error::InvalidBatchSize)
+            }
+            let seed = self.env().extension().fetch_random()?;
+            self.value = seed;
+            self.record_draw(seed);
+            self.batch = (0..n as u32).map(|counter| derive_random(seed, counter)).collect();
+
+            self.env().emit_event(RandomBatchUpdated{ seed, count: n });
+            Ok(self.batch.clone())
+        }
+
+        /// Returns the values produced by the last `update_many`
+        #[ink(message)]
+        pub fn get_many(&self) -> Vec<[u8; 32]> {
+            self.batch.clone()
+        }
+
+        /// Fetch a random value for a caller-provided subject without storing it
+        #[ink(message)]
+        pub fn fetch_random_seeded(&self, subject: Vec<u8>) -> Result<[u8; 32], RandomReadErr> {
+            self.seeded_random(subject)
+        }
+
+        /// Returns the number of draws recorded so far, including those that fell out of the history
+        #[ink(message)]
+        pub fn draw_count(&self) -> u32 {
+            self.draw_count
+        }
+
+        /// Returns the draw with the given index and its block number, `None` if it was never made
+        /// or is older than the last `HISTORY_SIZE` draws
+        #[ink(message)]
+        pub fn value_at(&self, index: u32) -> Option<([u8; 32], BlockNumber)> {
+            if index >= self.draw_count || self.draw_count - index > HISTORY_SIZE {
+                return None
+            }
+            self.history.get(&(index % HISTORY_SIZE)).copied()
+        }
+
+        /// Returns at most `limit` retained draws starting from the `offset`-th oldest one
+        #[ink(message)]
+        pub fn history(&self, offset: u32, limit: u32) -> Vec<([u8; 32], BlockNumber)> {
+            let first = self.draw_count.saturating_sub(HISTORY_SIZE);
+            let start = first.saturating_add(offset);
+            let end = core::cmp::min(start.saturating_add(limit), self.draw_count);
+            (start..end).filter_map(|index| self.value_at(index)).collect()
+        }
+
+        /// Appends a random value to the history, overwriting the oldest one when it is full
+        fn record_draw(&mut self, value: [u8; 32]) {
+            let block_number = self.env().block_number();
+            self.history.insert(self.draw_count % HISTORY_SIZE, (value, block_number));
+            self.draw_count += 1;
+        }
+
+        /// Builds the subject of the next `update` from the caller and the call nonce
+        fn next_subject(&mut self) -> Vec<u8> {
+            let subject = scale::Encode::encode(&(self.env().caller(), self.nonce));
+            self.nonce += 1;
+            subject
+        }
+
         /// Call Claim Created 
         #[ink(message)]
         pub fn create_claim(&mut self, claim: Vec<u8>) -> Result<(), RandomReadErr> {
             self.env().extension().create_claim( claim.clone() )?;
+            let caller = self.env().caller();
+            self.claim_owners.insert(claim.clone(), caller);
 
             self.env().emit_event(ClaimCreated{ claim: claim });
             Ok(())
         }
 
+        /// Transfer ownership of a claim registered by the caller to `to`, the claim leaves the contract
+        #[ink(message)]
+        pub fn transfer_claim(&mut self, claim: Vec<u8>, to: AccountId) -> Result<(), Error> {
+            let from = self.env().caller();
+            if self.claim_owners.get(&claim) != Some(&from) {
+                return Err(Error::NotClaimOwner)
+            }
+            self.env().extension().transfer_claim( claim.clone(), to )?;
+            self.claim_owners.take(&claim);
+
+            self.env().emit_event(ClaimTransferred{ claim, from, to });
+            Ok(())
+        }
+
+        /// Revoke a claim registered by the caller
+        #[ink(message)]
+        pub fn revoke_claim(&mut self, claim: Vec<u8>) -> Result<(), Error> {
+            let owner = self.env().caller();
+            if self.claim_owners.get(&claim) != Some(&owner) {
+                return Err(Error::NotClaimOwner)
+            }
+            self.env().extension().revoke_claim( claim.clone() )?;
+            self.claim_owners.take(&claim);
+
+            self.env().emit_event(ClaimRevoked{ claim, owner });
+            Ok(())
+        }
+
 
         #[ink(message)]
         pub fn create_kitty(&mut self) -> Result<(), RandomReadErr> {
@@ -140,10 +660,537 @@ mod randkey {
             ink_env::debug_println(&message);
 
             self.kitty_id = id;
+            let caller = self.env().caller();
+            self.kitty_owners.insert(id, caller);
+            self.cache_kitty(caller, id);
+
+            Ok(())
+        }
+
+        /// Breed a new kitty from two parents owned by the caller and store the child id
+        #[ink(message)]
+        pub fn breed_kitty(&mut self, parent1: u32, parent2: u32) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if self.kitty_owners.get(&parent1) != Some(&caller) || self.kitty_owners.get(&parent2) != Some(&caller) {
+                return Err(Error::NotKittyOwner)
+            }
+            let id = self.env().extension().breed_kitty(parent1, parent2)?;
+
+            self.env().emit_event(KittyBred{ kitty_id: id, parent1, parent2 });
+
+            self.kitty_id = id;
+            self.kitty_owners.insert(id, caller);
+            self.cache_kitty(caller, id);
+
+            Ok(())
+        }
+
+        /// Transfer a kitty owned by the caller to `to`, the kitty leaves the contract
+        /// and any listing of it is removed
+        #[ink(message)]
+        pub fn transfer_kitty(&mut self, kitty_id: u32, to: AccountId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if self.kitty_owners.get(&kitty_id) != Some(&caller) {
+                return Err(Error::NotKittyOwner)
+            }
+            self.env().extension().transfer_kitty(kitty_id, to)?;
+            self.kitty_owners.take(&kitty_id);
+            self.kitty_prices.take(&kitty_id);
+            self.uncache_kitty(caller, kitty_id);
+            self.cache_kitty(to, kitty_id);
+
+            self.env().emit_event(KittyTransferred{ kitty_id, from: caller, to });
+            Ok(())
+        }
+
+        /// Returns the ids of all kitties owned by `owner` according to the kitties pallet
+        #[ink(message)]
+        pub fn kitties_of(&self, owner: AccountId) -> Result<Vec<u32>, RandomReadErr> {
+            self.env().extension().kitties_of(owner)
+        }
+
+        /// Returns the account owning a kitty held by this contract
+        #[ink(message)]
+        pub fn kitty_holder(&self, kitty_id: u32) -> Option<AccountId> {
+            self.kitty_owners.get(&kitty_id).copied()
+        }
+
+        /// Returns the kitties of `owner` that were created, bred or transferred through this contract,
+        /// without calling the extension
+        #[ink(message)]
+        pub fn cached_kitties_of(&self, owner: AccountId) -> Vec<u32> {
+            self.owned_kitties.get(&owner).cloned().unwrap_or_default()
+        }
+
+        /// Adds a kitty to the cached list of its owner
+        fn cache_kitty(&mut self, owner: AccountId, kitty_id: u32) {
+            let mut kitties = self.cached_kitties_of(owner);
+            if !kitties.contains(&kitty_id) {
+                kitties.push(kitty_id);
+                self.owned_kitties.insert(owner, kitties);
+            }
+        }
+
+        /// Removes a kitty from the cached list of its previous owner
+        fn uncache_kitty(&mut self, owner: AccountId, kitty_id: u32) {
+            let mut kitties = self.cached_kitties_of(owner);
+            kitties.retain(|id| *id != kitty_id);
+            if kitties.is_empty() {
+                self.owned_kitties.take(&owner);
+            } else {
+                self.owned_kitties.insert(owner, kitties);
+            }
+        }
+
+        /// Returns the owner of a claim and the block it was registered in
+        #[ink(message)]
+        pub fn claim_owner(&self, claim: Vec<u8>) -> Result<Option<(AccountId, BlockNumber)>, RandomReadErr> {
+            self.env().extension().claim_owner(claim)
+        }
+
+        /// List a kitty owned by the caller for sale, `None` removes the listing.
+        /// The kitty stays in the custody of the contract until it is sold
+        #[ink(message)]
+        pub fn set_kitty_price(&mut self, kitty_id: u32, price: Option<Balance>) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if self.kitty_owners.get(&kitty_id) != Some(&caller) {
+                return Err(Error::NotKittyOwner)
+            }
+            // Only kitties the pallet records as held by the contract can be moved on a sale
+            if self.env().extension().kitty_owner(kitty_id)? != Some(self.env().account_id()) {
+                return Err(Error::NotKittyOwner)
+            }
+            match price {
+                Some(price) => { self.kitty_prices.insert(kitty_id, (caller, price)); }
+                None => { self.kitty_prices.take(&kitty_id); }
+            }
+
+            self.env().emit_event(KittyPriceSet{ kitty_id, seller: caller, price });
+            Ok(())
+        }
+
+        /// Returns the seller and price of a listed kitty
+        #[ink(message)]
+        pub fn kitty_price(&self, kitty_id: u32) -> Option<(AccountId, Balance)> {
+            self.kitty_prices.get(&kitty_id).copied()
+        }
+
+        /// Buy a listed kitty, the whole transferred balance is forwarded to the seller
+        /// and the kitty is transferred out of the contract to the caller through the extension.
+        /// Any failure reverts the call so the buyer keeps the transferred balance
+        #[ink(message, payable)]
+        pub fn buy_kitty(&mut self, kitty_id: u32) -> Result<(), Error> {
+            let (seller, price) = match self.kitty_prices.get(&kitty_id) {
+                Some(listing) => *listing,
+                None => Self::revert(Error::KittyNotForSale),
+            };
+            let paid = self.env().transferred_balance();
+            if paid < price {
+                Self::revert(Error::InsufficientPayment)
+            }
+            if self.kitty_owners.get(&kitty_id) != Some(&seller) {
+                Self::revert(Error::KittyNotForSale)
+            }
+            let buyer = self.env().caller();
+            if self.env().transfer(seller, paid).is_err() {
+                Self::revert(Error::PaymentFailed)
+            }
+            if let Err(error) = self.env().extension().transfer_kitty(kitty_id, buyer) {
+                Self::revert(error.into())
+            }
+            self.kitty_owners.take(&kitty_id);
+            self.kitty_prices.take(&kitty_id);
+            self.uncache_kitty(seller, kitty_id);
+            self.cache_kitty(buyer, kitty_id);
+
+            self.env().emit_event(KittySold{ kitty_id, seller, buyer, price: paid });
+            self.env().emit_event(KittyTransferred{ kitty_id, from: seller, to: buyer });
+            Ok(())
+        }
+
+        /// Returns whether `signature` is a valid sr25519 signature of `message` by `pubkey`
+        #[ink(message)]
+        pub fn verify_sr25519(&self, pubkey: [u8; 32], message: Vec<u8>, signature: [u8; 64]) -> Result<bool, RandomReadErr> {
+            self.env().extension().verify_sr25519(pubkey, message, signature)
+        }
+
+        /// Returns whether `signature` is a valid ecdsa signature of `message` by the compressed `pubkey`
+        #[ink(message)]
+        pub fn verify_ecdsa(&self, pubkey: Vec<u8>, message: Vec<u8>, signature: Vec<u8>) -> Result<bool, RandomReadErr> {
+            self.env().extension().verify_ecdsa(pubkey, message, signature)
+        }
+
+        /// Returns the raw value under a safelisted runtime storage key
+        #[ink(message)]
+        pub fn read_storage(&self, key: Vec<u8>) -> Result<Option<Vec<u8>>, RandomReadErr> {
+            self.env().extension().read_storage(key)
+        }
+
+        /// Returns the total issuance of the native token
+        #[ink(message)]
+        pub fn total_issuance(&self) -> Result<Option<Balance>, Error> {
+            self.read_storage_as(&TOTAL_ISSUANCE_KEY)
+        }
+
+        /// Returns the runtime timestamp of the current block
+        #[ink(message)]
+        pub fn runtime_now(&self) -> Result<Option<Timestamp>, Error> {
+            self.read_storage_as(&TIMESTAMP_NOW_KEY)
+        }
+
+        /// Reads a runtime storage value and decodes it as `T`
+        fn read_storage_as<T: scale::Decode>(&self, key: &[u8]) -> Result<Option<T>, Error> {
+            match self.env().extension().read_storage(key.to_vec())? {
+                Some(raw) => T::decode(&mut &raw[..])
+                    .map(Some)
+                    .map_err(|_| Error::InvalidStorageValue),
+                None => Ok(None),
+            }
+        }
+
+        /// Returns the current staking era index
+        #[ink(message)]
+        pub fn current_era(&self) -> Result<Option<u32>, RandomReadErr> {
+            self.env().extension().current_era()
+        }
+
+        /// Returns the ideal number of validators
+        #[ink(message)]
+        pub fn validator_count(&self) -> Result<u32, RandomReadErr> {
+            self.env().extension().validator_count()
+        }
+
+        /// Returns the active bonded balance of a stash account
+        #[ink(message)]
+        pub fn staked_of(&self, account: AccountId) -> Result<Balance, RandomReadErr> {
+            self.env().extension().staked_of(account)
+        }
+
+        /// Anchor `data` in a runtime remark event, the blake2-256 hash of the data is
+        /// emitted as a topic so the remark can be matched with the contract event
+        #[ink(message)]
+        pub fn dispatch_remark(&mut self, data: Vec<u8>) -> Result<(), RandomReadErr> {
+            let data_hash = blake2_256(&data);
+            self.env().extension().dispatch_remark(data)?;
+
+            let caller = self.env().caller();
+            self.env().emit_event(RemarkDispatched{ caller, data_hash });
+            Ok(())
+        }
+
+        /// Dispatch a safelisted runtime call with the contract as origin, only the owner can call it
+        #[ink(message)]
+        pub fn dispatch_call(&mut self, call: Vec<u8>) -> Result<(), Error> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner)
+            }
+            let call_hash = blake2_256(&call);
+            self.env().extension().dispatch_call(call)?;
+
+            self.env().emit_event(CallDispatched{ call_hash });
+            Ok(())
+        }
+
+        /// Returns the oracle price of `asset` and the timestamp it was last updated at
+        #[ink(message)]
+        pub fn fetch_price(&self, asset: u32) -> Result<(u128, Timestamp), RandomReadErr> {
+            self.env().extension().fetch_price(asset)
+        }
+
+        /// Fetch the oracle price of `asset` and store it
+        #[ink(message)]
+        pub fn update_price(&mut self, asset: u32) -> Result<(), RandomReadErr> {
+            let (price, timestamp) = self.env().extension().fetch_price(asset)?;
+            self.prices.insert(asset, (price, timestamp));
+
+            self.env().emit_event(PriceUpdated{ asset, price, timestamp });
+            Ok(())
+        }
+
+        /// Returns the price of `asset` stored by the last `update_price`
+        #[ink(message)]
+        pub fn price_of(&self, asset: u32) -> Option<(u128, Timestamp)> {
+            self.prices.get(&asset).copied()
+        }
+
+        /// Returns the DNA of a kitty
+        #[ink(message)]
+        pub fn kitty_dna(&self, kitty_id: u32) -> Result<[u8; 16], RandomReadErr> {
+            self.env().extension().kitty_dna(kitty_id)
+        }
+
+        /// Returns the owner of a kitty
+        #[ink(message)]
+        pub fn kitty_owner(&self, kitty_id: u32) -> Result<Option<AccountId>, RandomReadErr> {
+            self.env().extension().kitty_owner(kitty_id)
+        }
+
+        /// Returns a random value in `[min, max]` derived from freshly fetched randomness
+        #[ink(message)]
+        pub fn random_in_range(&self, min: u64, max: u64) -> Result<u64, Error> {
+            if min > max {
+                return Err(Error::InvalidRange)
+            }
+            let seed = self.env().extension().fetch_random()?;
+            Ok(uniform_in_range(seed, min, max))
+        }
+
+        /// Returns the owner of the contract
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        /// Set the lottery ticket price and how many blocks a round stays open, only the owner can call it
+        #[ink(message)]
+        pub fn set_lottery(&mut self, ticket_price: Balance, round_blocks: BlockNumber) -> Result<(), Error> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner)
+            }
+            self.lottery_ticket_price = ticket_price;
+            self.lottery_round_blocks = round_blocks;
+            Ok(())
+        }
+
+        /// Returns the current round, ticket price, pot, number of entries and the block the round ends at
+        #[ink(message)]
+        pub fn lottery_info(&self) -> (u32, Balance, Balance, u32, Option<BlockNumber>) {
+            (
+                self.lottery_round,
+                self.lottery_ticket_price,
+                self.lottery_pot,
+                self.lottery_entrant_count,
+                self.lottery_ends_at,
+            )
+        }
+
+        /// Enter the current lottery round by paying exactly the ticket price,
+        /// the first entry of a round starts its countdown. A rejected entry reverts the call
+        /// so the transferred balance is returned
+        #[ink(message, payable)]
+        pub fn enter(&mut self) -> Result<(), Error> {
+            if self.lottery_ticket_price == 0 {
+                Self::revert(Error::LotteryDisabled)
+            }
+            if self.env().transferred_balance() != self.lottery_ticket_price {
+                Self::revert(Error::InvalidTicketPayment)
+            }
+            let caller = self.env().caller();
+            if self.lottery_ends_at.is_none() {
+                self.lottery_ends_at = Some(self.env().block_number() + self.lottery_round_blocks);
+            }
+            self.lottery_entrants.insert((self.lottery_round, self.lottery_entrant_count), caller);
+            self.lottery_entrant_count += 1;
+            self.lottery_pot += self.lottery_ticket_price;
+
+            self.env().emit_event(LotteryEntered{ round: self.lottery_round, account: caller });
+            Ok(())
+        }
+
+        /// Pick a winner of the current round with fresh randomness and pay out the whole pot,
+        /// anyone can draw after the round ends and the owner can draw at any time
+        #[ink(message)]
+        pub fn draw(&mut self) -> Result<(), Error> {
+            if self.lottery_entrant_count == 0 {
+                return Err(Error::NoEntrants)
+            }
+            let ends_at = self.lottery_ends_at.unwrap_or_default();
+            if self.env().caller() != self.owner && self.env().block_number() < ends_at {
+                return Err(Error::LotteryNotOver)
+            }
+            let round = self.lottery_round;
+            let subject = scale::Encode::encode(&(b"lottery", round));
+            let seed = self.seeded_random(subject)?;
+            let index = uniform_in_range(seed, 0, self.lottery_entrant_count as u64 - 1) as u32;
+            let winner = match self.lottery_entrants.get(&(round, index)) {
+                Some(winner) => *winner,
+                None => return Err(Error::NoEntrants),
+            };
+            let prize = self.lottery_pot;
+            if self.env().transfer(winner, prize).is_err() {
+                return Err(Error::PaymentFailed)
+            }
+            self.record_draw(seed);
+            self.lottery_round += 1;
+            self.lottery_ends_at = None;
+            self.lottery_pot = 0;
+            self.lottery_entrant_count = 0;
+
+            self.env().emit_event(WinnerSelected{ round, winner, prize });
+            Ok(())
+        }
 
+        /// Bet on a guess from 1 to 6 with the transferred balance as the wager, a correct guess
+        /// pays out 6x the wager minus the house edge from the bankroll. The bet is rolled with the
+        /// seed of the next block, which isn't known when it is placed, and the payout of a win is
+        /// reserved until the bet is settled. A rejected bet reverts the call
+        #[ink(message, payable)]
+        pub fn roll(&mut self, guess: u8) -> Result<BlockNumber, Error> {
+            if guess == 0 || guess > DICE_FACES {
+                Self::revert(Error::InvalidGuess)
+            }
+            let wager = self.env().transferred_balance();
+            if wager == 0 {
+                Self::revert(Error::ZeroWager)
+            }
+            let player = self.env().caller();
+            if self.dice_bets.contains_key(&player) {
+                Self::revert(Error::BetPending)
+            }
+            let win_payout = self.dice_payout(wager);
+            if win_payout > self.dice_bankroll + wager - self.dice_reserved {
+                Self::revert(Error::InsufficientBankroll)
+            }
+            if let Err(error) = self.dice_seed() {
+                Self::revert(error.into())
+            }
+            let settle_at = self.env().block_number() + 1;
+            self.dice_bets.insert(player, (guess, wager, win_payout, settle_at));
+            self.dice_bankroll += wager;
+            self.dice_reserved += win_payout;
+            self.dice_total_wagered += wager;
+
+            self.env().emit_event(DiceBetPlaced{ player, guess, wager, settle_at });
+            Ok(settle_at)
+        }
+
+        /// Roll the dice of the pending bet of `player` and pay out a win, anyone can call it from the
+        /// settlement block on. The roll uses the seed of the settlement block, which the first dice
+        /// call of that block fixes, so settling later gives the same result and a losing roll can't
+        /// be retried. A bet whose settlement block has no seed or that isn't settled within
+        /// `DICE_SETTLE_WINDOW` blocks is refunded with a result of 0
+        #[ink(message)]
+        pub fn settle_roll(&mut self, player: AccountId) -> Result<u8, Error> {
+            let (guess, wager, win_payout, settle_at) = match self.dice_bets.get(&player) {
+                Some(bet) => *bet,
+                None => return Err(Error::NoPendingBet),
+            };
+            let block_number = self.env().block_number();
+            if block_number < settle_at {
+                return Err(Error::BetNotReady)
+            }
+            if block_number == settle_at {
+                self.dice_seed()?;
+            }
+            let seed = match self.dice_seeds.get(&settle_at) {
+                Some(seed) if block_number <= settle_at + DICE_SETTLE_WINDOW => *seed,
+                _ => {
+                    self.dice_bets.take(&player);
+                    self.dice_reserved -= win_payout;
+                    self.dice_bankroll -= wager;
+                    self.dice_total_wagered -= wager;
+                    if self.env().transfer(player, wager).is_err() {
+                        Self::revert(Error::PaymentFailed)
+                    }
+                    self.env().emit_event(DiceRefunded{ player, wager });
+                    return Ok(0)
+                }
+            };
+            let result = uniform_in_range(blake2_256(&scale::Encode::encode(&(seed, player))), 1, DICE_FACES as u64) as u8;
+            let payout = if result == guess { win_payout } else { 0 };
+            self.dice_bets.take(&player);
+            self.dice_reserved -= win_payout;
+            self.dice_bankroll -= payout;
+            self.dice_total_paid += payout;
+            if payout > 0 && self.env().transfer(player, payout).is_err() {
+                Self::revert(Error::PaymentFailed)
+            }
+
+            self.env().emit_event(DiceRolled{ player, guess, result, wager, payout });
+            Ok(result)
+        }
+
+        /// Returns the dice seed of the current block, fetching and recording it on the first call of the block
+        fn dice_seed(&mut self) -> Result<[u8; 32], RandomReadErr> {
+            let block_number = self.env().block_number();
+            if let Some(seed) = self.dice_seeds.get(&block_number) {
+                return Ok(*seed)
+            }
+            let subject = scale::Encode::encode(&(b"dice", block_number));
+            let seed = self.seeded_random(subject)?;
+            self.record_draw(seed);
+            self.dice_seeds.insert(block_number, seed);
+            Ok(seed)
+        }
+
+        /// Returns the guess, wager, payout on a win and settlement block of the pending bet of `player`
+        #[ink(message)]
+        pub fn pending_roll(&self, player: AccountId) -> Option<(u8, Balance, Balance, BlockNumber)> {
+            self.dice_bets.get(&player).copied()
+        }
+
+        /// Returns the dice bankroll including the reserved payouts, house edge in basis points,
+        /// total wagered and total paid out
+        #[ink(message)]
+        pub fn dice_stats(&self) -> (Balance, u32, Balance, Balance) {
+            (self.dice_bankroll, self.dice_house_edge_bps, self.dice_total_wagered, self.dice_total_paid)
+        }
+
+        /// Add the transferred balance to the dice bankroll, only the owner can call it and a call
+        /// from anyone else reverts so the transferred balance is returned
+        #[ink(message, payable)]
+        pub fn fund_bankroll(&mut self) -> Result<(), Error> {
+            if self.env().caller() != self.owner {
+                Self::revert(Error::NotOwner)
+            }
+            self.dice_bankroll += self.env().transferred_balance();
             Ok(())
         }
 
+        /// Withdraw the part of the dice bankroll that isn't reserved for pending bets to the owner
+        #[ink(message)]
+        pub fn withdraw_bankroll(&mut self, amount: Balance) -> Result<(), Error> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner)
+            }
+            if amount > self.dice_bankroll - self.dice_reserved {
+                return Err(Error::InsufficientBankroll)
+            }
+            if self.env().transfer(self.owner, amount).is_err() {
+                return Err(Error::PaymentFailed)
+            }
+            self.dice_bankroll -= amount;
+            Ok(())
+        }
+
+        /// Set the house edge in basis points, only the owner can call it
+        #[ink(message)]
+        pub fn set_house_edge(&mut self, edge_bps: u32) -> Result<(), Error> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner)
+            }
+            if edge_bps as Balance > BPS_DENOMINATOR {
+                return Err(Error::InvalidHouseEdge)
+            }
+            self.dice_house_edge_bps = edge_bps;
+            Ok(())
+        }
+
+        /// Payout of a winning wager: a fair 6x multiple reduced by the house edge
+        fn dice_payout(&self, wager: Balance) -> Balance {
+            let fair = wager * DICE_FACES as Balance;
+            fair - fair * self.dice_house_edge_bps as Balance / BPS_DENOMINATOR
+        }
+
+        /// Fetches the random value for `subject` through the chain extension
+        #[cfg(not(test))]
+        fn seeded_random(&self, subject: Vec<u8>) -> Result<[u8; 32], RandomReadErr> {
+            self.env().extension().fetch_random_seeded(subject)
+        }
+
+        /// The off-chain environment has no runtime behind the chain extension,
+        /// tests serve the random values from a mock instead
+        #[cfg(test)]
+        fn seeded_random(&self, _subject: Vec<u8>) -> Result<[u8; 32], RandomReadErr> {
+            Ok(tests::mock_random::next())
+        }
+
+        /// Returns `error` and reverts every state change of the call, so payable messages
+        /// don't keep the transferred balance when they fail
+        fn revert(error: Error) -> ! {
+            ink_env::return_value::<Result<(), Error>>(ink_env::ReturnFlags::default().set_reverted(true), &Err(error))
+        }
+
         /// Simply returns the current value of our `bool`.
         #[ink(message)]
         pub fn get(&self) -> [u8; 32] {
@@ -157,6 +1204,47 @@ mod randkey {
         }
     }
 
+    /// Maps a 32 byte seed to `[min, max]` without modulo bias by rejection sampling:
+    /// the seed is split into u64 words and words from the biased tail are discarded,
+    /// rehashing the seed with a counter in the unlikely case that all of them are rejected.
+    fn uniform_in_range(seed: [u8; 32], min: u64, max: u64) -> u64 {
+        let span = (max - min).wrapping_add(1);
+        let mut words = seed;
+        let mut counter: u32 = 0;
+        loop {
+            for chunk in words.chunks(8) {
+                let mut word = [0u8; 8];
+                word.copy_from_slice(chunk);
+                let value = u64::from_le_bytes(word);
+                if span == 0 {
+                    // The range covers every u64 value
+                    return value
+                }
+                let limit = u64::MAX - (u64::MAX % span + 1) % span;
+                if value <= limit {
+                    return min + value % span
+                }
+            }
+            counter += 1;
+            words = derive_random(seed, counter);
+        }
+    }
+
+    /// Derives an independent value from a seed by hashing it together with a counter
+    fn derive_random(seed: [u8; 32], counter: u32) -> [u8; 32] {
+        let mut input = [0u8; 36];
+        input[..32].copy_from_slice(&seed);
+        input[32..].copy_from_slice(&counter.to_le_bytes());
+        blake2_256(&input)
+    }
+
+    /// Returns the blake2-256 hash of `input`
+    fn blake2_256(input: &[u8]) -> [u8; 32] {
+        let mut output = [0u8; 32];
+        ink_env::hash_bytes::<ink_env::hash::Blake2x256>(input, &mut output);
+        output
+    }
+
     /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
     /// module and test functions are marked with a `#[test]` attribute.
     /// The below code is technically just normal Rust code.
@@ -164,19 +1252,255 @@ mod randkey {
     mod tests {
         /// Imports all the definitions from the outer scope so we can use them here.
         use super::*;
+        use ink_lang as ink;
+
+        /// Serves the values of `fetch_random_seeded`: the configured seed, with its last
+        /// byte bumped after every draw so consecutive draws differ
+        pub mod mock_random {
+            use std::cell::RefCell;
+
+            thread_local! {
+                static SEED: RefCell<[u8; 32]> = const { RefCell::new([0; 32]) };
+            }
+
+            pub fn set_seed(seed: [u8; 32]) {
+                SEED.with(|current| *current.borrow_mut() = seed);
+            }
+
+            pub fn next() -> [u8; 32] {
+                SEED.with(|current| {
+                    let mut current = current.borrow_mut();
+                    let seed = *current;
+                    current[31] = current[31].wrapping_add(1);
+                    seed
+                })
+            }
+        }
+
+        /// A seed whose first u64 word is `word`, the rest of the seed is zero
+        fn seed_with_word(word: u64) -> [u8; 32] {
+            let mut seed = [0u8; 32];
+            seed[..8].copy_from_slice(&word.to_le_bytes());
+            seed
+        }
+
+        fn accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
+            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+                .expect("Cannot get accounts")
+        }
+
+        fn contract_id() -> AccountId {
+            ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .unwrap_or([0x0; 32].into())
+        }
+
+        fn balance_of(account: AccountId) -> Balance {
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(account)
+                .expect("Cannot get account balance")
+        }
+
+        /// Runs `f` as `caller` with `amount` transferred to the contract
+        fn call_as<R>(caller: AccountId, amount: Balance, f: impl FnOnce() -> R) -> R {
+            ink_env::test::push_execution_context::<ink_env::DefaultEnvironment>(
+                caller,
+                contract_id(),
+                1000000,
+                amount,
+                ink_env::test::CallData::new(ink_env::call::Selector::new([0x00; 4])),
+            );
+            let result = f();
+            ink_env::test::pop_execution_context();
+            result
+        }
 
         /// We test if the default constructor does its job.
-        #[test]
+        #[ink::test]
         fn default_works() {
             let randkey = Randkey::default();
-            assert_eq!(randkey.get(), false);
+            assert_eq!(randkey.get(), [0; 32]);
+            assert_eq!(randkey.draw_count(), 0);
         }
 
         /// We test a simple use case of our contract.
-        #[test]
+        #[ink::test]
         fn it_works() {
-            let randkey = RandExtension::default();
-            assert_eq!(randkey.get(), [0; 32]);
+            mock_random::set_seed([7; 32]);
+            let mut randkey = Randkey::new([1; 32]);
+            assert_eq!(randkey.get(), [1; 32]);
+            assert_eq!(randkey.update(), Ok(()));
+            assert_eq!(randkey.get(), [7; 32]);
+            assert_eq!(randkey.value_at(0).map(|(value, _)| value), Some([7; 32]));
+        }
+
+        #[ink::test]
+        fn uniform_in_range_rejects_the_biased_tail() {
+            // 2^64 is 4 modulo 6, so the last 4 values of a word are rejected for a span of 6
+            let limit = u64::MAX - 4;
+            assert_eq!(uniform_in_range(seed_with_word(limit), 1, 6), 1 + limit % 6);
+            assert_eq!(uniform_in_range(seed_with_word(2), 1, 6), 3);
+
+            // A rejected word falls through to the next word of the seed
+            let mut seed = seed_with_word(limit + 1);
+            seed[8..16].copy_from_slice(&4u64.to_le_bytes());
+            assert_eq!(uniform_in_range(seed, 1, 6), 5);
+
+            // When every word is rejected the seed is rehashed with a counter
+            let seed = [0xff; 32];
+            let value = uniform_in_range(seed, 1, 6);
+            assert_eq!(value, uniform_in_range(derive_random(seed, 1), 1, 6));
+            assert!((1..=6).contains(&value));
+
+            // A span dividing 2^64 rejects nothing
+            assert_eq!(uniform_in_range([0xff; 32], 0, 1), 1);
+            // A single value range and the full u64 range
+            assert_eq!(uniform_in_range([0xff; 32], 9, 9), 9);
+            assert_eq!(uniform_in_range(seed_with_word(12345), 0, u64::MAX), 12345);
+        }
+
+        #[ink::test]
+        fn history_keeps_the_latest_draws_in_order() {
+            mock_random::set_seed([0; 32]);
+            let mut randkey = Randkey::default();
+            let total = HISTORY_SIZE + 2;
+            for _ in 0..total {
+                assert_eq!(randkey.update(), Ok(()));
+            }
+            assert_eq!(randkey.draw_count(), total);
+
+            // The draw with index n returned a seed ending in n, the two oldest were overwritten
+            assert_eq!(randkey.value_at(0), None);
+            assert_eq!(randkey.value_at(1), None);
+            assert_eq!(randkey.value_at(2).map(|(value, _)| value[31]), Some(2));
+            assert_eq!(randkey.value_at(total - 1).map(|(value, _)| value[31]), Some(total as u8 - 1));
+            assert_eq!(randkey.value_at(total), None);
+
+            let history = randkey.history(0, HISTORY_SIZE + 10);
+            assert_eq!(history.len(), HISTORY_SIZE as usize);
+            let last_bytes: Vec<u8> = history.iter().map(|(value, _)| value[31]).collect();
+            assert_eq!(last_bytes, (2..total as u8).collect::<Vec<u8>>());
+
+            let page = randkey.history(10, 3);
+            let last_bytes: Vec<u8> = page.iter().map(|(value, _)| value[31]).collect();
+            assert_eq!(last_bytes, vec![12, 13, 14]);
+            assert_eq!(randkey.history(HISTORY_SIZE, 3), Vec::new());
+        }
+
+        #[ink::test]
+        fn lottery_draw_pays_the_selected_entrant() {
+            let accounts = accounts();
+            // A span of 3 divides 2^64 - 1, so a first word of 1 selects the second entrant
+            mock_random::set_seed(seed_with_word(1));
+            let mut randkey = Randkey::default();
+            assert_eq!(randkey.set_lottery(10, 5), Ok(()));
+
+            for entrant in [accounts.alice, accounts.bob, accounts.charlie] {
+                assert_eq!(call_as(entrant, 10, || randkey.enter()), Ok(()));
+            }
+            assert_eq!(randkey.lottery_info(), (0, 10, 30, 3, Some(5)));
+
+            // Only the owner can draw before the round ends
+            assert_eq!(call_as(accounts.bob, 0, || randkey.draw()), Err(Error::LotteryNotOver));
+
+            // The off-chain environment doesn't credit transferred balances to the contract
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(contract_id(), 30)
+                .expect("Cannot set account balance");
+            let bob_balance = balance_of(accounts.bob);
+            assert_eq!(randkey.draw(), Ok(()));
+            assert_eq!(balance_of(accounts.bob), bob_balance + 30);
+            assert_eq!(randkey.draw_count(), 1);
+
+            // The next round starts empty
+            assert_eq!(randkey.lottery_info(), (1, 10, 0, 0, None));
+            assert_eq!(randkey.draw(), Err(Error::NoEntrants));
+        }
+
+        /// The face a bet of `player` settling with `seed` rolls
+        fn dice_result(seed: [u8; 32], player: AccountId) -> u8 {
+            uniform_in_range(blake2_256(&scale::Encode::encode(&(seed, player))), 1, DICE_FACES as u64) as u8
+        }
+
+        fn advance_blocks(blocks: u32) {
+            for _ in 0..blocks {
+                ink_env::test::advance_block::<ink_env::DefaultEnvironment>()
+                    .expect("Cannot advance block");
+            }
+        }
+
+        #[ink::test]
+        fn dice_reserves_and_pays_out_from_the_bankroll() {
+            let accounts = accounts();
+            // The roll in block 0 fetches the seed ending in 0, the settlement in block 1 the one ending in 1
+            mock_random::set_seed([0; 32]);
+            let mut seed = [0; 32];
+            seed[31] = 1;
+            let guess = dice_result(seed, accounts.bob);
+            let mut randkey = Randkey::default();
+            assert_eq!(call_as(accounts.alice, 1000, || randkey.fund_bankroll()), Ok(()));
+            assert_eq!(randkey.set_house_edge(100), Ok(()));
+
+            // A 100 wager pays 600 minus the 1% edge on a win, which is reserved until settlement
+            assert_eq!(call_as(accounts.bob, 100, || randkey.roll(guess)), Ok(1));
+            assert_eq!(randkey.pending_roll(accounts.bob), Some((guess, 100, 594, 1)));
+            assert_eq!(randkey.dice_stats(), (1100, 100, 100, 0));
+            assert_eq!(randkey.withdraw_bankroll(507), Err(Error::InsufficientBankroll));
+            assert_eq!(randkey.settle_roll(accounts.bob), Err(Error::BetNotReady));
+
+            advance_blocks(1);
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(contract_id(), 1100)
+                .expect("Cannot set account balance");
+            let bob_balance = balance_of(accounts.bob);
+            assert_eq!(randkey.settle_roll(accounts.bob), Ok(guess));
+            assert_eq!(balance_of(accounts.bob), bob_balance + 594);
+            assert_eq!(randkey.pending_roll(accounts.bob), None);
+            assert_eq!(randkey.dice_stats(), (506, 100, 100, 594));
+            assert_eq!(randkey.settle_roll(accounts.bob), Err(Error::NoPendingBet));
+
+            // Nobody rolled in the settlement block of this bet, so it has no seed and is refunded
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(contract_id(), 606)
+                .expect("Cannot set account balance");
+            assert_eq!(call_as(accounts.bob, 100, || randkey.roll(guess)), Ok(2));
+            advance_blocks(2);
+            let bob_balance = balance_of(accounts.bob);
+            assert_eq!(randkey.settle_roll(accounts.bob), Ok(0));
+            assert_eq!(balance_of(accounts.bob), bob_balance + 100);
+            assert_eq!(randkey.dice_stats(), (506, 100, 100, 594));
+            assert_eq!(randkey.withdraw_bankroll(506), Ok(()));
+            assert_eq!(randkey.dice_stats(), (0, 100, 100, 594));
+        }
+
+        #[ink::test]
+        fn dice_settles_late_with_the_seed_of_the_settlement_block() {
+            let accounts = accounts();
+            mock_random::set_seed([0; 32]);
+            let mut randkey = Randkey::default();
+            assert_eq!(call_as(accounts.alice, 10_000, || randkey.fund_bankroll()), Ok(()));
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(contract_id(), 100_000)
+                .expect("Cannot set account balance");
+
+            // Bob's bet settles in block 1, where Charlie's roll fixes the seed ending in 1
+            assert_eq!(call_as(accounts.bob, 100, || randkey.roll(1)), Ok(1));
+            advance_blocks(1);
+            assert_eq!(call_as(accounts.charlie, 100, || randkey.roll(1)), Ok(2));
+            let mut seed = [0; 32];
+            seed[31] = 1;
+            let result = dice_result(seed, accounts.bob);
+
+            // Django's roll fixes the seed of block 2, where Charlie's bet settles
+            advance_blocks(1);
+            assert_eq!(call_as(accounts.django, 100, || randkey.roll(1)), Ok(3));
+
+            // Settling a few blocks late still rolls with the seed of the settlement block
+            advance_blocks(4);
+            assert_eq!(randkey.settle_roll(accounts.bob), Ok(result));
+            let paid = if result == 1 { 600 } else { 0 };
+            assert_eq!(randkey.dice_stats(), (10_300 - paid, 0, 300, paid));
+
+            // Charlie's bet is refunded once the settlement window has passed, even though it has a seed
+            advance_blocks(DICE_SETTLE_WINDOW);
+            let charlie_balance = balance_of(accounts.charlie);
+            assert_eq!(randkey.settle_roll(accounts.charlie), Ok(0));
+            assert_eq!(balance_of(accounts.charlie), charlie_balance + 100);
+            assert_eq!(randkey.dice_stats(), (10_200 - paid, 0, 200, paid));
         }
     }
 }